@@ -0,0 +1,269 @@
+use crate::{
+    block::BlockDevice,
+    error::FatError,
+    fat::{FatEntry, ENTRIES_PER_SECTOR},
+    time::TimeSource,
+    volume::Fat32Volume,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// One bit per data cluster, set when the cluster is free.
+pub(crate) struct FreeBitmap {
+    words: Vec<u64>,
+    clusters: u32,
+}
+
+impl FreeBitmap {
+    /// Bytes needed for a volume of `cluster_count` clusters.
+    pub(crate) fn size_for(cluster_count: u32) -> usize {
+        cluster_count.div_ceil(64) as usize * 8
+    }
+
+    /// Build the bitmap by reading the whole FAT once.
+    fn build<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>) -> Self {
+        let cluster_count = volume.cluster_count();
+        let mut map = Self { words: vec![0; cluster_count.div_ceil(64) as usize], clusters: cluster_count };
+        let mut sector = [0u8; 512];
+
+        for index in 0..(cluster_count + 2).div_ceil(ENTRIES_PER_SECTOR) {
+            volume.read_fat_sector(index as u64, &mut sector);
+            for cluster in index * ENTRIES_PER_SECTOR..(index + 1) * ENTRIES_PER_SECTOR {
+                if volume.is_data_cluster(cluster) && FatEntry::in_sector(&sector, cluster) == FatEntry::Free {
+                    map.set(cluster, true);
+                }
+            }
+        }
+        map
+    }
+
+    /// Mark `cluster` free or in use.
+    pub(crate) fn set(&mut self, cluster: u32, free: bool) {
+        let bit = cluster.wrapping_sub(2);
+        if bit >= self.clusters {
+            return;
+        }
+        let word = &mut self.words[bit as usize / 64];
+        if free {
+            *word |= 1 << (bit % 64);
+        } else {
+            *word &= !(1 << (bit % 64));
+        }
+    }
+
+    /// Whether `cluster` is free.
+    fn is_free(&self, cluster: u32) -> bool {
+        let bit = cluster - 2;
+        self.words[bit as usize / 64] & (1 << (bit % 64)) != 0
+    }
+
+    /// Up to `count` free clusters from `start` on, wrapping around once.
+    fn find(&self, start: u32, count: usize, found: &mut Vec<u32>) {
+        let start = start - 2;
+        let first_word = (start / 64) as usize;
+        let words = self.words.len();
+
+        // the first word is visited twice: its high bits first, its low
+        // bits after wrapping around
+        for step in 0..=words {
+            let index = (first_word + step) % words;
+            let mut word = self.words[index];
+            if step == 0 {
+                word &= u64::MAX << (start % 64);
+            } else if step == words {
+                word &= !(u64::MAX << (start % 64));
+            }
+
+            while word != 0 && found.len() < count {
+                found.push(index as u32 * 64 + word.trailing_zeros() + 2);
+                word &= word - 1;
+            }
+            if found.len() == count {
+                return;
+            }
+        }
+    }
+}
+
+/// How clusters are chosen for new data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllocationStrategy {
+    /// Lowest free clusters, searching from cluster 2 every time. Keeps
+    /// data packed at the start of the volume.
+    FirstFit,
+    /// Resume after the last allocation, wrapping around at the end of
+    /// the volume. Spreads writes over the whole volume and searches the
+    /// least.
+    #[default]
+    NextFit,
+    /// Smallest run of free clusters that holds the whole request, so the
+    /// data is contiguous while large runs are saved for large files. A
+    /// file growing through a handle, whose final size is unknown,
+    /// continues right after its last cluster when possible and otherwise
+    /// moves to the largest free run. Needs a full pass over the FAT
+    /// unless the free bitmap is enabled; falls back to next-fit when no
+    /// run is long enough.
+    BestFitContiguous,
+}
+
+/// What newly allocated clusters are for, which decides where best-fit
+/// puts them.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Placement {
+    /// A chain whose whole length is known up front.
+    Whole,
+    /// More clusters for a growing file, after its last cluster if it has
+    /// one.
+    Append(Option<u32>),
+}
+
+/// Find `count` free clusters with the volume's allocation strategy, in
+/// the order they should be chained.
+pub(crate) fn find_free_clusters<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    count: usize,
+) -> Result<Vec<u32>, FatError> {
+    let strategy = volume.allocation_strategy;
+    find_free_clusters_with(volume, count, strategy, Placement::Whole)
+}
+
+/// Find `count` free clusters with `strategy`, placed for `placement`.
+///
+/// The free bitmap is used when one is enabled and fits in its size
+/// limit, building it on first use; otherwise the FAT is scanned a whole
+/// sector at a time.
+pub(crate) fn find_free_clusters_with<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    count: usize,
+    strategy: AllocationStrategy,
+    placement: Placement,
+) -> Result<Vec<u32>, FatError> {
+    let mut found = Vec::with_capacity(count);
+    if count == 0 {
+        return Ok(found);
+    }
+
+    if volume.free_bitmap.is_none()
+        && FreeBitmap::size_for(volume.cluster_count()) <= volume.free_bitmap_limit
+    {
+        volume.free_bitmap = Some(FreeBitmap::build(volume));
+    }
+
+    let next_fit = volume.next_free_hint.filter(|&hint| volume.is_data_cluster(hint)).unwrap_or(2);
+    let start = match strategy {
+        AllocationStrategy::FirstFit => 2,
+        AllocationStrategy::NextFit => next_fit,
+        AllocationStrategy::BestFitContiguous => match placement {
+            Placement::Whole => pick_run(volume, count, false),
+            Placement::Append(last) => last
+                .map(|cluster| cluster + 1)
+                .filter(|&next| run_is_free(volume, next, count))
+                .or_else(|| pick_run(volume, 1, true)),
+        }
+        .unwrap_or(next_fit),
+    };
+
+    match &volume.free_bitmap {
+        Some(map) => map.find(start, count, &mut found),
+        None => {
+            let end = volume.cluster_count() + 2;
+            for (from, to) in [(start, end), (2, start)] {
+                if found.len() < count {
+                    scan_fat(volume, from, to, |cluster, free| {
+                        if free {
+                            found.push(cluster);
+                        }
+                        found.len() < count
+                    });
+                }
+            }
+        }
+    }
+
+    if found.len() < count {
+        return Err(FatError::NoFreeClusters);
+    }
+    Ok(found)
+}
+
+/// Start of the smallest run of at least `count` free clusters, or of the
+/// largest run if `largest` is set.
+fn pick_run<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, count: usize, largest: bool) -> Option<u32> {
+    let mut best: Option<(u32, usize)> = None;
+    let mut run = (0, 0);
+    let mut end_run = |run: (u32, usize)| {
+        let better = best.is_none_or(|(_, len)| if largest { run.1 > len } else { run.1 < len });
+        if run.1 >= count && better {
+            best = Some(run);
+        }
+    };
+
+    for_each_cluster(volume, 2, volume.cluster_count() + 2, |cluster, free| {
+        if free {
+            if run.1 == 0 {
+                run.0 = cluster;
+            }
+            run.1 += 1;
+        } else if run.1 > 0 {
+            end_run(run);
+            run.1 = 0;
+        }
+        true
+    });
+    end_run(run);
+    best.map(|(start, _)| start)
+}
+
+/// Whether the `count` clusters from `start` on are all free.
+fn run_is_free<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, start: u32, count: usize) -> bool {
+    let Some(end) = start.checked_add(count as u32) else { return false };
+    if end > volume.cluster_count() + 2 {
+        return false;
+    }
+    let mut all_free = true;
+    for_each_cluster(volume, start, end, |_, free| {
+        all_free &= free;
+        all_free
+    });
+    all_free
+}
+
+/// Call `f` with each cluster from `from` to `to` and whether it is free,
+/// from the free bitmap if there is one, until `f` returns false.
+fn for_each_cluster<B: BlockDevice, T: TimeSource>(
+    volume: &Fat32Volume<B, T>,
+    from: u32,
+    to: u32,
+    mut f: impl FnMut(u32, bool) -> bool,
+) {
+    match &volume.free_bitmap {
+        Some(map) => {
+            for cluster in from..to {
+                if !f(cluster, map.is_free(cluster)) {
+                    return;
+                }
+            }
+        }
+        None => scan_fat(volume, from, to, f),
+    }
+}
+
+/// Call `f` with each cluster from `from` to `to` and whether its FAT
+/// entry is free, reading each FAT sector once, until `f` returns false.
+fn scan_fat<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, from: u32, to: u32, mut f: impl FnMut(u32, bool) -> bool) {
+    let mut sector = [0u8; 512];
+    let mut cluster = from;
+
+    while cluster < to {
+        let index = cluster / ENTRIES_PER_SECTOR;
+        let sector_end = ((index + 1) * ENTRIES_PER_SECTOR).min(to);
+        volume.read_fat_sector(index as u64, &mut sector);
+
+        for cluster in cluster..sector_end {
+            if !f(cluster, FatEntry::in_sector(&sector, cluster) == FatEntry::Free) {
+                return;
+            }
+        }
+        cluster = sector_end;
+    }
+}
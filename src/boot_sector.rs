@@ -1,8 +1,33 @@
 use crate::error::FatError;
 
-/// FAT32 BIOS Parameter Block (partial)
+/// FAT variant, determined by the number of data clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    /// FAT type of a volume with `cluster_count` data clusters, as the
+    /// Microsoft specification defines it. The type string in the boot
+    /// sector is informational only and never consulted.
+    pub fn from_cluster_count(cluster_count: u32) -> Self {
+        if cluster_count < 4085 {
+            FatType::Fat12
+        } else if cluster_count < 65525 {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        }
+    }
+}
+
+/// FAT32 BIOS Parameter Block and extended boot record
 #[derive(Debug, Clone)]
 pub struct BootSector {
+    /// OEM name, e.g. `MSWIN4.1`
+    pub oem_name: [u8; 8],
     /// Bytes per sector (usually 512)
     pub bytes_per_sector: u16,
     /// Sectors per cluster
@@ -11,25 +36,52 @@ pub struct BootSector {
     pub reserved_sectors: u16,
     /// Number of FATs
     pub fat_count: u8,
+    /// Media descriptor (0xF8 for fixed media)
+    pub media_descriptor: u8,
     /// Total sectors of the volume
     pub total_sectors: u32,
     /// Size of one FAT in sectors
     pub fat_size_sectors: u32,
+    /// FAT mirroring flags: bit 7 disables mirroring, bits 0-3 select the active FAT
+    pub ext_flags: u16,
+    /// Filesystem version, major in the high byte
+    pub fs_version: u16,
     /// Root directory first cluster
     pub root_cluster: u32,
+    /// Sector number of the FSInfo structure
+    pub fsinfo_sector: u16,
+    /// Sector number of the backup boot sector (0 if none)
+    pub backup_boot_sector: u16,
+    /// Volume serial number, if the extended boot signature is present
+    pub volume_id: Option<u32>,
+    /// Volume label, if the extended boot signature is present
+    pub volume_label: Option<[u8; 11]>,
+    /// Filesystem type string, informational only
+    pub fs_type: Option<[u8; 8]>,
 }
 
 impl BootSector {
     /// Parse a FAT32 boot sector from raw sector data.
+    ///
+    /// The BPB is validated against the Microsoft FAT specification and
+    /// volumes whose cluster count makes them FAT12 or FAT16 are refused
+    /// with [`FatError::NotFat32`].
     pub fn parse(sector: &[u8]) -> Result<Self, FatError> {
         if sector.len() < 512 {
             return Err(FatError::InvalidBootSector);
         }
+        if sector[510] != 0x55 || sector[511] != 0xAA {
+            return Err(FatError::MissingBootSignature);
+        }
+
+        let mut oem_name = [0u8; 8];
+        oem_name.copy_from_slice(&sector[3..11]);
 
         let bytes_per_sector = u16::from_le_bytes([sector[11], sector[12]]);
         let sectors_per_cluster = sector[13];
         let reserved_sectors = u16::from_le_bytes([sector[14], sector[15]]);
         let fat_count = sector[16];
+        let media_descriptor = sector[21];
 
         let total_sectors_16 = u16::from_le_bytes([sector[19], sector[20]]);
         let total_sectors_32 =
@@ -41,25 +93,142 @@ impl BootSector {
             total_sectors_32
         };
 
-        let fat_size_sectors =
+        let root_entry_count = u16::from_le_bytes([sector[17], sector[18]]);
+        let fat_size_16 = u16::from_le_bytes([sector[22], sector[23]]);
+        let fat_size_32 =
             u32::from_le_bytes([sector[36], sector[37], sector[38], sector[39]]);
+        let fat_size_sectors = if fat_size_16 != 0 {
+            fat_size_16 as u32
+        } else {
+            fat_size_32
+        };
+
+        let ext_flags = u16::from_le_bytes([sector[40], sector[41]]);
+        let fs_version = u16::from_le_bytes([sector[42], sector[43]]);
 
         let root_cluster =
             u32::from_le_bytes([sector[44], sector[45], sector[46], sector[47]]);
 
-        Ok(Self {
+        let fsinfo_sector = u16::from_le_bytes([sector[48], sector[49]]);
+        let backup_boot_sector = u16::from_le_bytes([sector[50], sector[51]]);
+
+        // 0x28 only guarantees the serial number, 0x29 adds label and type
+        let boot_signature = sector[66];
+        let volume_id = (boot_signature == 0x28 || boot_signature == 0x29)
+            .then(|| u32::from_le_bytes([sector[67], sector[68], sector[69], sector[70]]));
+        let (volume_label, fs_type) = if boot_signature == 0x29 {
+            let mut label = [0u8; 11];
+            label.copy_from_slice(&sector[71..82]);
+            let mut fs_type = [0u8; 8];
+            fs_type.copy_from_slice(&sector[82..90]);
+            (Some(label), Some(fs_type))
+        } else {
+            (None, None)
+        };
+
+        let boot = Self {
+            oem_name,
             bytes_per_sector,
             sectors_per_cluster,
             reserved_sectors,
             fat_count,
+            media_descriptor,
             total_sectors,
             fat_size_sectors,
+            ext_flags,
+            fs_version,
             root_cluster,
-        })
+            fsinfo_sector,
+            backup_boot_sector,
+            volume_id,
+            volume_label,
+            fs_type,
+        };
+        boot.validate(root_entry_count, fat_size_16)?;
+        Ok(boot)
+    }
+
+    /// Check the BPB fields the rest of the crate relies on.
+    fn validate(&self, root_entry_count: u16, fat_size_16: u16) -> Result<(), FatError> {
+        // BlockDevice works in 512-byte sectors
+        if self.bytes_per_sector != 512 {
+            return Err(FatError::UnsupportedSectorSize);
+        }
+        if !self.sectors_per_cluster.is_power_of_two() {
+            return Err(FatError::InvalidSectorsPerCluster);
+        }
+        if self.reserved_sectors == 0 {
+            return Err(FatError::InvalidReservedSectors);
+        }
+        if self.fat_count == 0 {
+            return Err(FatError::InvalidFatCount);
+        }
+        if self.media_descriptor != 0xF0 && self.media_descriptor < 0xF8 {
+            return Err(FatError::InvalidMediaDescriptor);
+        }
+        if self.fat_size_sectors == 0 {
+            return Err(FatError::InvalidFatSize);
+        }
+
+        let root_dir_sectors = (root_entry_count as u32 * 32).div_ceil(512);
+        let metadata_sectors = self.reserved_sectors as u64
+            + self.fat_count as u64 * self.fat_size_sectors as u64
+            + root_dir_sectors as u64;
+        if self.total_sectors as u64 <= metadata_sectors {
+            return Err(FatError::InvalidTotalSectors);
+        }
+
+        let cluster_count =
+            ((self.total_sectors as u64 - metadata_sectors) / self.sectors_per_cluster as u64) as u32;
+        match FatType::from_cluster_count(cluster_count) {
+            FatType::Fat32 => {}
+            other => return Err(FatError::NotFat32(other)),
+        }
+        if root_entry_count != 0 || fat_size_16 != 0 {
+            return Err(FatError::InvalidBootSector);
+        }
+        if self.fs_version != 0 {
+            return Err(FatError::UnsupportedFatVersion);
+        }
+
+        // the FAT must have an entry for every cluster, plus the two reserved ones
+        if (self.fat_size_sectors as u64 * 512 / 4) < cluster_count as u64 + 2 {
+            return Err(FatError::InvalidFatSize);
+        }
+        if self.root_cluster < 2 || self.root_cluster - 2 >= cluster_count {
+            return Err(FatError::InvalidRootCluster);
+        }
+        Ok(())
+    }
+
+    /// First sector of the data region.
+    pub fn first_data_sector(&self) -> u64 {
+        self.reserved_sectors as u64 + self.fat_count as u64 * self.fat_size_sectors as u64
+    }
+
+    /// Number of data clusters.
+    pub fn cluster_count(&self) -> u32 {
+        let data_sectors = self.total_sectors as u64 - self.first_data_sector();
+        (data_sectors / self.sectors_per_cluster as u64) as u32
+    }
+
+    /// FAT variant implied by the cluster count.
+    pub fn fat_type(&self) -> FatType {
+        FatType::from_cluster_count(self.cluster_count())
     }
 
     /// Cluster size in bytes.
     pub fn cluster_size(&self) -> u32 {
         self.bytes_per_sector as u32 * self.sectors_per_cluster as u32
     }
+
+    /// Whether updates are mirrored to every FAT copy.
+    pub fn fat_mirroring(&self) -> bool {
+        self.ext_flags & 0x80 == 0
+    }
+
+    /// Index of the FAT in use when mirroring is disabled.
+    pub fn active_fat(&self) -> u8 {
+        (self.ext_flags & 0x0F) as u8
+    }
 }
@@ -1,4 +1,94 @@
-use alloc::vec::Vec;
+use core::{fmt, ops};
+
+use crate::{
+    block::BlockDevice,
+    error::FatError,
+    fat::ClusterChain,
+    lfn::{LfnAccumulator, LongName},
+    time::{DateTime, NoTimeSource, TimeSource, FAT_EPOCH},
+    volume::Fat32Volume,
+};
+
+/// Size of a directory entry slot in bytes.
+pub const DIR_ENTRY_SIZE: usize = 32;
+/// Maximum number of slots in a directory (2 MiB), per the FAT specification.
+pub const MAX_DIR_ENTRIES: usize = 65_536;
+
+/// Attribute bits of a directory entry.
+pub const ATTR_READ_ONLY: u8 = 0x01;
+pub const ATTR_HIDDEN: u8 = 0x02;
+pub const ATTR_SYSTEM: u8 = 0x04;
+pub const ATTR_VOLUME_ID: u8 = 0x08;
+pub const ATTR_DIRECTORY: u8 = 0x10;
+pub const ATTR_ARCHIVE: u8 = 0x20;
+/// Combination marking a VFAT long file name slot.
+pub const ATTR_LONG_NAME: u8 = ATTR_READ_ONLY | ATTR_HIDDEN | ATTR_SYSTEM | ATTR_VOLUME_ID;
+
+/// Typed set of the attribute bits of a directory entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    pub const READ_ONLY: Self = Self(ATTR_READ_ONLY);
+    pub const HIDDEN: Self = Self(ATTR_HIDDEN);
+    pub const SYSTEM: Self = Self(ATTR_SYSTEM);
+    pub const VOLUME_ID: Self = Self(ATTR_VOLUME_ID);
+    pub const DIRECTORY: Self = Self(ATTR_DIRECTORY);
+    pub const ARCHIVE: Self = Self(ATTR_ARCHIVE);
+
+    /// The empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Set from an on-disk attribute byte; the two reserved bits are dropped.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & 0x3F)
+    }
+
+    /// The attribute byte.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Whether every attribute of `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Set the attributes of `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Clear the attributes of `other`.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl ops::BitOr for Attributes {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for Attributes {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// Names of the `.` and `..` entries at the start of every subdirectory.
+pub const DOT_NAME: &[u8; 11] = b".          ";
+pub const DOTDOT_NAME: &[u8; 11] = b"..         ";
+
+/// First name byte of the slot terminating a directory.
+pub(crate) const ENTRY_END: u8 = 0x00;
+/// First name byte of a deleted slot.
+pub(crate) const ENTRY_DELETED: u8 = 0xE5;
 
 /// FAT32 short directory entry
 #[derive(Debug)]
@@ -7,4 +97,294 @@ pub struct DirEntry {
     pub attributes: u8,
     pub first_cluster: u32,
     pub size: u32,
+    /// VFAT long name, when a valid LFN chain precedes the short entry.
+    pub long_name: Option<LongName>,
+    pub created: DateTime,
+    pub modified: DateTime,
+    /// Last access date; FAT does not record the time of day.
+    pub accessed: DateTime,
+    /// Where the entry was read from; `None` for the root directory.
+    pub location: Option<EntryLocation>,
+}
+
+/// Where an entry's slots live, for updating or deleting it in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryLocation {
+    /// First cluster of the directory holding the entry
+    pub dir_cluster: u32,
+    /// Index of the short entry's slot within the directory
+    pub index: u32,
+    /// Number of LFN slots right before the short entry
+    pub lfn_slots: u8,
+    /// Position of the short entry
+    pub pos: SlotPos,
+}
+
+impl DirEntry {
+    /// Parse a 32-byte short directory entry.
+    pub fn parse(raw: &[u8]) -> Self {
+        let mut name = [0u8; 11];
+        name.copy_from_slice(&raw[0..11]);
+        // 0x05 stands in for a leading 0xE5 byte, which would mean "deleted"
+        if name[0] == 0x05 {
+            name[0] = ENTRY_DELETED;
+        }
+
+        let word = |offset: usize| u16::from_le_bytes([raw[offset], raw[offset + 1]]);
+        let high = word(20) as u32;
+        let low = word(26) as u32;
+
+        Self {
+            name,
+            attributes: raw[11],
+            first_cluster: (high << 16) | low,
+            size: u32::from_le_bytes([raw[28], raw[29], raw[30], raw[31]]),
+            long_name: None,
+            created: DateTime::from_fat(word(16), word(14), raw[13]),
+            modified: DateTime::from_fat(word(24), word(22), 0),
+            accessed: DateTime::from_fat(word(18), 0, 0),
+            location: None,
+        }
+    }
+
+    /// Synthesized entry standing for the root directory, which has none.
+    pub(crate) fn root(root_cluster: u32) -> Self {
+        Self {
+            name: [b' '; 11],
+            attributes: ATTR_DIRECTORY,
+            first_cluster: root_cluster,
+            size: 0,
+            long_name: None,
+            created: FAT_EPOCH,
+            modified: FAT_EPOCH,
+            accessed: FAT_EPOCH,
+            location: None,
+        }
+    }
+
+    /// Attribute bits as a typed set.
+    pub fn attributes(&self) -> Attributes {
+        Attributes::from_bits(self.attributes)
+    }
+
+    /// Whether this entry describes a directory.
+    pub fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+
+    /// Whether this entry is marked read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.attributes & ATTR_READ_ONLY != 0
+    }
+
+    /// The 8.3 name as `NAME.EXT`, without padding.
+    pub fn short_name_chars(&self) -> impl Iterator<Item = char> + '_ {
+        let base = trim_padding(&self.name[..8]);
+        let ext = trim_padding(&self.name[8..]);
+        let dot: &[u8] = if ext.is_empty() { b"" } else { b"." };
+        base.iter().chain(dot).chain(ext).map(|&b| b as char)
+    }
+
+    /// Case-insensitive comparison of `name` against both the long and
+    /// the short name of this entry.
+    pub fn matches(&self, name: &str) -> bool {
+        if let Some(long_name) = &self.long_name {
+            if eq_ignore_case(long_name.chars(), name.chars()) {
+                return true;
+            }
+        }
+        eq_ignore_case(self.short_name_chars(), name.chars())
+    }
+}
+
+impl fmt::Display for DirEntry {
+    /// Long name if present, otherwise the 8.3 name as `NAME.EXT`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(long_name) = &self.long_name {
+            return long_name.fmt(f);
+        }
+        self.short_name_chars().try_for_each(|c| fmt::Write::write_char(f, c))
+    }
+}
+
+fn eq_ignore_case(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> bool {
+    a.flat_map(char::to_uppercase).eq(b.flat_map(char::to_uppercase))
+}
+
+/// Strip the space padding of a short name field.
+pub(crate) fn trim_padding(field: &[u8]) -> &[u8] {
+    let len = field.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+    &field[..len]
+}
+
+/// Location of a 32-byte directory slot on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotPos {
+    /// Sector holding the slot
+    pub lba: u64,
+    /// Byte offset of the slot within the sector
+    pub offset: usize,
+}
+
+/// Every 32-byte slot of a directory's cluster chain, including those
+/// past the end-of-directory marker.
+pub(crate) struct RawSlots<'a, B: BlockDevice, T: TimeSource> {
+    volume: &'a Fat32Volume<B, T>,
+    dir_cluster: u32,
+    chain: ClusterChain<'a, B, T>,
+    buf: [u8; 512],
+    lba: u64,
+    sectors_left: u8,
+    offset: usize,
+    done: bool,
+}
+
+impl<'a, B: BlockDevice, T: TimeSource> RawSlots<'a, B, T> {
+    /// Cluster 0 refers to the root directory, as in `..` entries.
+    pub(crate) fn new(volume: &'a Fat32Volume<B, T>, dir_cluster: u32) -> Self {
+        let dir_cluster = if dir_cluster == 0 { volume.root_cluster() } else { dir_cluster };
+        Self {
+            volume,
+            dir_cluster,
+            chain: volume.cluster_chain(dir_cluster),
+            buf: [0u8; 512],
+            lba: 0,
+            sectors_left: 0,
+            offset: 512,
+            done: false,
+        }
+    }
+
+    /// First cluster of the directory, with 0 resolved to the root.
+    pub(crate) fn dir_cluster(&self) -> u32 {
+        self.dir_cluster
+    }
+}
+
+impl<B: BlockDevice, T: TimeSource> Iterator for RawSlots<'_, B, T> {
+    type Item = Result<(SlotPos, [u8; DIR_ENTRY_SIZE]), FatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.offset == 512 {
+            if self.sectors_left > 0 {
+                self.lba += 1;
+                self.sectors_left -= 1;
+            } else {
+                match self.chain.next() {
+                    Some(Ok(cluster)) => {
+                        self.lba = self.volume.cluster_lba(cluster);
+                        self.sectors_left = self.volume.boot.sectors_per_cluster - 1;
+                    }
+                    Some(Err(err)) => {
+                        self.done = true;
+                        return Some(Err(err));
+                    }
+                    None => {
+                        self.done = true;
+                        return None;
+                    }
+                }
+            }
+            self.volume.device.read_sector(self.lba, &mut self.buf);
+            self.offset = 0;
+        }
+
+        let pos = SlotPos { lba: self.lba, offset: self.offset };
+        let mut slot = [0u8; DIR_ENTRY_SIZE];
+        slot.copy_from_slice(&self.buf[self.offset..self.offset + DIR_ENTRY_SIZE]);
+        self.offset += DIR_ENTRY_SIZE;
+        Some(Ok((pos, slot)))
+    }
+}
+
+/// Iterator over the entries of a directory.
+///
+/// Follows the directory's cluster chain one sector at a time and yields
+/// every short entry until the end-of-directory marker. Long file name
+/// slots are attached to the entry they precede; deleted slots and the
+/// volume label are skipped.
+pub struct DirIter<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    slots: RawSlots<'a, B, T>,
+    lfn: LfnAccumulator,
+    /// Number of slots consumed so far.
+    index: u32,
+    done: bool,
+}
+
+impl<'a, B: BlockDevice, T: TimeSource> DirIter<'a, B, T> {
+    /// Start iterating the directory whose first cluster is `dir_cluster`.
+    pub fn new(volume: &'a Fat32Volume<B, T>, dir_cluster: u32) -> Self {
+        Self {
+            slots: RawSlots::new(volume, dir_cluster),
+            lfn: LfnAccumulator::new(),
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// Next raw 32-byte slot and its position, or `None` once the end
+    /// marker or the end of the chain is reached.
+    pub(crate) fn next_slot(&mut self) -> Option<Result<(SlotPos, [u8; DIR_ENTRY_SIZE]), FatError>> {
+        if self.done {
+            return None;
+        }
+
+        self.index += 1;
+        match self.slots.next() {
+            Some(Ok((pos, slot))) if slot[0] != ENTRY_END => Some(Ok((pos, slot))),
+            Some(Err(err)) => {
+                self.done = true;
+                Some(Err(err))
+            }
+            _ => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<B: BlockDevice, T: TimeSource> Iterator for DirIter<'_, B, T> {
+    type Item = Result<DirEntry, FatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (pos, slot) = match self.next_slot()? {
+                Ok(found) => found,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if slot[0] == ENTRY_DELETED {
+                self.lfn.reset();
+                continue;
+            }
+            let attributes = slot[11];
+            if attributes & ATTR_LONG_NAME == ATTR_LONG_NAME {
+                self.lfn.push(&slot);
+                continue;
+            }
+            if attributes & (ATTR_VOLUME_ID | ATTR_DIRECTORY) == ATTR_VOLUME_ID {
+                self.lfn.reset();
+                continue;
+            }
+
+            let mut entry = DirEntry::parse(&slot);
+            // the checksum covers the name as stored, with a leading 0x05
+            // still in place
+            let mut stored_name = [0u8; 11];
+            stored_name.copy_from_slice(&slot[0..11]);
+            entry.long_name = self.lfn.finish(&stored_name);
+            entry.location = Some(EntryLocation {
+                dir_cluster: self.slots.dir_cluster(),
+                index: self.index - 1,
+                lfn_slots: if entry.long_name.is_some() { self.lfn.slots() } else { 0 },
+                pos,
+            });
+            return Some(Ok(entry));
+        }
+    }
 }
@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use crate::boot_sector::FatType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FatError {
     InvalidBootSector,
     IoError,
@@ -6,4 +8,35 @@ pub enum FatError {
     NoFreeClusters,
     InvalidCluster,
     NoFreeDirectoryEntry,
+    BadCluster,
+    FreeClusterInChain,
+    ClusterChainLoop,
+    NotADirectory,
+    IsADirectory,
+    ChainTooShort,
+    InvalidSeek,
+    BufferTooSmall,
+    InvalidFsInfo,
+    MissingBootSignature,
+    UnsupportedSectorSize,
+    InvalidSectorsPerCluster,
+    InvalidReservedSectors,
+    InvalidFatCount,
+    InvalidMediaDescriptor,
+    InvalidFatSize,
+    InvalidTotalSectors,
+    InvalidRootCluster,
+    UnsupportedFatVersion,
+    NotFat32(FatType),
+    InvalidFileName,
+    NoFreeShortName,
+    AlreadyExists,
+    ReadOnly,
+    DirectoryNotEmpty,
+    IsRootDirectory,
+    MoveIntoSubdirectory,
+    InvalidOpenOptions,
+    NotReadable,
+    NotWritable,
+    FileTooLarge,
 }
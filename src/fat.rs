@@ -0,0 +1,249 @@
+use crate::{
+    block::BlockDevice,
+    error::FatError,
+    time::{NoTimeSource, TimeSource},
+    volume::Fat32Volume,
+};
+
+/// Only the low 28 bits of a FAT32 entry are meaningful.
+pub const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
+/// Marker for a free cluster.
+pub const FAT_FREE: u32 = 0;
+/// Marker for a cluster flagged as unusable.
+pub const FAT_BAD_CLUSTER: u32 = 0x0FFF_FFF7;
+/// Any value at or above this one ends a chain.
+pub const FAT_EOC_MIN: u32 = 0x0FFF_FFF8;
+/// End-of-chain value written by this crate.
+pub const FAT_END_OF_CHAIN: u32 = 0x0FFF_FFFF;
+/// FAT entries per 512-byte FAT sector.
+pub(crate) const ENTRIES_PER_SECTOR: u32 = 128;
+
+/// Decoded value of a FAT32 entry, ignoring the upper four reserved bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatEntry {
+    /// The cluster is free.
+    Free,
+    /// The chain continues at this cluster.
+    Next(u32),
+    /// The cluster is flagged as unusable.
+    Bad,
+    /// The chain ends at this cluster.
+    EndOfChain,
+    /// A value the spec reserves: 1 or 0x0FFFFFF0 to 0x0FFFFFF6.
+    Reserved(u32),
+}
+
+impl FatEntry {
+    /// Decode a raw entry as stored in the FAT.
+    pub fn from_raw(raw: u32) -> Self {
+        match raw & FAT_ENTRY_MASK {
+            FAT_FREE => Self::Free,
+            FAT_BAD_CLUSTER => Self::Bad,
+            value if value >= FAT_EOC_MIN => Self::EndOfChain,
+            value @ (1 | 0x0FFF_FFF0..=0x0FFF_FFF6) => Self::Reserved(value),
+            value => Self::Next(value),
+        }
+    }
+
+    /// The low 28 bits to store for this entry.
+    pub fn to_raw(self) -> u32 {
+        match self {
+            Self::Free => FAT_FREE,
+            Self::Next(value) | Self::Reserved(value) => value & FAT_ENTRY_MASK,
+            Self::Bad => FAT_BAD_CLUSTER,
+            Self::EndOfChain => FAT_END_OF_CHAIN,
+        }
+    }
+
+    /// Decode the entry of `cluster` from the FAT sector holding it.
+    pub(crate) fn in_sector(sector: &[u8], cluster: u32) -> Self {
+        let offset = (cluster % ENTRIES_PER_SECTOR) as usize * 4;
+        Self::from_raw(u32::from_le_bytes([
+            sector[offset],
+            sector[offset + 1],
+            sector[offset + 2],
+            sector[offset + 3],
+        ]))
+    }
+}
+
+/// Read access to the FAT of a volume, one data cluster at a time.
+pub struct FatTable<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    volume: &'a Fat32Volume<B, T>,
+}
+
+impl<'a, B: BlockDevice, T: TimeSource> FatTable<'a, B, T> {
+    pub(crate) fn new(volume: &'a Fat32Volume<B, T>) -> Self {
+        Self { volume }
+    }
+
+    /// Entry of `cluster`, which must be a data cluster of the volume.
+    pub fn get(&self, cluster: u32) -> Result<FatEntry, FatError> {
+        read_entry(self.volume, cluster)
+    }
+}
+
+/// Read and write access to the FAT of a volume.
+///
+/// Writes go through the FAT cache and keep the upper four reserved bits
+/// of each entry, the free bitmap and the FSInfo free count up to date.
+#[cfg(feature = "alloc")]
+pub struct FatTableMut<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    volume: &'a mut Fat32Volume<B, T>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, B: BlockDevice, T: TimeSource> FatTableMut<'a, B, T> {
+    pub(crate) fn new(volume: &'a mut Fat32Volume<B, T>) -> Self {
+        Self { volume }
+    }
+
+    /// Entry of `cluster`, which must be a data cluster of the volume.
+    pub fn get(&self, cluster: u32) -> Result<FatEntry, FatError> {
+        read_entry(self.volume, cluster)
+    }
+
+    /// Set the entry of `cluster` and return the previous one. Both
+    /// `cluster` and the target of [`FatEntry::Next`] must be data
+    /// clusters of the volume.
+    pub fn set(&mut self, cluster: u32, entry: FatEntry) -> Result<FatEntry, FatError> {
+        check_cluster(self.volume, cluster)?;
+        if let FatEntry::Next(next) = entry {
+            check_cluster(self.volume, next)?;
+        }
+
+        let offset = (cluster % ENTRIES_PER_SECTOR) as usize * 4;
+        let mut old = 0;
+        self.volume.modify_fat_sector((cluster / ENTRIES_PER_SECTOR) as u64, |sector| {
+            let bytes = &mut sector[offset..offset + 4];
+            old = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let raw = (old & !FAT_ENTRY_MASK) | entry.to_raw();
+            bytes.copy_from_slice(&raw.to_le_bytes());
+        });
+
+        let old = FatEntry::from_raw(old);
+        let is_free = entry == FatEntry::Free;
+        if let Some(map) = &mut self.volume.free_bitmap {
+            map.set(cluster, is_free);
+        }
+        match (old == FatEntry::Free, is_free) {
+            (true, false) => self.volume.update_fsinfo(|info| {
+                info.free_count = info.free_count.and_then(|free| free.checked_sub(1));
+            }),
+            (false, true) => self.volume.update_fsinfo(|info| {
+                info.free_count = info.free_count.and_then(|free| free.checked_add(1));
+            }),
+            _ => {}
+        }
+        Ok(old)
+    }
+}
+
+/// Read the entry of data cluster `cluster`.
+fn read_entry<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, cluster: u32) -> Result<FatEntry, FatError> {
+    check_cluster(volume, cluster)?;
+    let mut sector = [0u8; 512];
+    volume.read_fat_sector((cluster / ENTRIES_PER_SECTOR) as u64, &mut sector);
+    Ok(FatEntry::in_sector(&sector, cluster))
+}
+
+/// Reject clusters outside the volume's data clusters, even where the FAT
+/// has room for more entries.
+fn check_cluster<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, cluster: u32) -> Result<(), FatError> {
+    if volume.is_data_cluster(cluster) {
+        Ok(())
+    } else {
+        Err(FatError::InvalidCluster)
+    }
+}
+
+/// Iterator over the clusters of a FAT chain.
+///
+/// Yields each cluster of the chain in order and stops after the cluster
+/// marked end-of-chain. A link pointing to a free, bad or out-of-range
+/// cluster is reported as an error after the last valid cluster, and a
+/// chain that loops back on itself is reported as
+/// [`FatError::ClusterChainLoop`] instead of being followed forever.
+pub struct ClusterChain<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    volume: &'a Fat32Volume<B, T>,
+    next: Link,
+    sector: [u8; 512],
+    cached_sector: Option<u64>,
+    // Brent's cycle detection: `tortoise` is re-anchored every `power` steps.
+    tortoise: Option<u32>,
+    power: u32,
+    steps: u32,
+}
+
+enum Link {
+    Cluster(u32),
+    Error(FatError),
+    Done,
+}
+
+impl<'a, B: BlockDevice, T: TimeSource> ClusterChain<'a, B, T> {
+    /// Start a chain at `first_cluster`. Cluster 0 is an empty chain.
+    pub fn new(volume: &'a Fat32Volume<B, T>, first_cluster: u32) -> Self {
+        let next = if first_cluster == 0 {
+            Link::Done
+        } else {
+            Link::Cluster(first_cluster)
+        };
+
+        Self {
+            volume,
+            next,
+            sector: [0u8; 512],
+            cached_sector: None,
+            tortoise: None,
+            power: 1,
+            steps: 0,
+        }
+    }
+
+    /// Read the FAT entry of `cluster`, keeping the last FAT sector cached.
+    fn read_entry(&mut self, cluster: u32) -> FatEntry {
+        let sector_index = (cluster / ENTRIES_PER_SECTOR) as u64;
+        if self.cached_sector != Some(sector_index) {
+            self.volume.read_fat_sector(sector_index, &mut self.sector);
+            self.cached_sector = Some(sector_index);
+        }
+        FatEntry::in_sector(&self.sector, cluster)
+    }
+}
+
+impl<B: BlockDevice, T: TimeSource> Iterator for ClusterChain<'_, B, T> {
+    type Item = Result<u32, FatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cluster = match core::mem::replace(&mut self.next, Link::Done) {
+            Link::Cluster(cluster) => cluster,
+            Link::Error(err) => return Some(Err(err)),
+            Link::Done => return None,
+        };
+
+        if !self.volume.is_data_cluster(cluster) {
+            return Some(Err(FatError::InvalidCluster));
+        }
+
+        if self.tortoise == Some(cluster) {
+            return Some(Err(FatError::ClusterChainLoop));
+        }
+        self.steps += 1;
+        if self.tortoise.is_none() || self.steps == self.power {
+            self.tortoise = Some(cluster);
+            self.power = self.power.saturating_mul(2);
+            self.steps = 0;
+        }
+
+        self.next = match self.read_entry(cluster) {
+            FatEntry::EndOfChain => Link::Done,
+            FatEntry::Bad => Link::Error(FatError::BadCluster),
+            FatEntry::Free => Link::Error(FatError::FreeClusterInChain),
+            FatEntry::Next(next) if self.volume.is_data_cluster(next) => Link::Cluster(next),
+            FatEntry::Next(_) | FatEntry::Reserved(_) => Link::Error(FatError::InvalidCluster),
+        };
+
+        Some(Ok(cluster))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{block::BlockDevice, boot_sector::BootSector};
+use alloc::vec::Vec;
+
+/// Number of FAT sectors cached unless configured otherwise.
+pub(crate) const DEFAULT_FAT_CACHE_SECTORS: usize = 8;
+
+/// A cached sector of the first FAT.
+struct CachedSector {
+    index: u64,
+    data: [u8; 512],
+    dirty: bool,
+    /// Value of the use counter when last accessed, for LRU eviction.
+    last_use: u64,
+}
+
+/// Write-back cache of FAT sectors.
+///
+/// Changes are made to a single copy of each sector and written to every
+/// FAT on eviction or [`flush`](Self::flush). A capacity of 0 writes
+/// every change through immediately.
+pub(crate) struct FatCache {
+    sectors: Vec<CachedSector>,
+    capacity: usize,
+    uses: u64,
+}
+
+impl FatCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { sectors: Vec::new(), capacity, uses: 0 }
+    }
+
+    /// Copy sector `index` into `buf` if it is cached.
+    pub(crate) fn read(&self, index: u64, buf: &mut [u8]) -> bool {
+        match self.sectors.iter().find(|sector| sector.index == index) {
+            Some(sector) => {
+                buf.copy_from_slice(&sector.data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply `update` to sector `index`, loading it first if needed and
+    /// evicting the least recently used sector when the cache is full.
+    pub(crate) fn modify<B: BlockDevice>(
+        &mut self,
+        device: &mut B,
+        boot: &BootSector,
+        index: u64,
+        update: impl FnOnce(&mut [u8; 512]),
+    ) {
+        self.uses += 1;
+        if let Some(sector) = self.sectors.iter_mut().find(|sector| sector.index == index) {
+            update(&mut sector.data);
+            sector.dirty = true;
+            sector.last_use = self.uses;
+            return;
+        }
+
+        let mut data = [0u8; 512];
+        device.read_sector(boot.reserved_sectors as u64 + index, &mut data);
+        update(&mut data);
+
+        if self.capacity == 0 {
+            write_copies(device, boot, index, &data);
+            return;
+        }
+        if self.sectors.len() == self.capacity {
+            self.evict(device, boot);
+        }
+        self.sectors.push(CachedSector { index, data, dirty: true, last_use: self.uses });
+    }
+
+    /// Write every dirty sector to all FAT copies.
+    pub(crate) fn flush<B: BlockDevice>(&mut self, device: &mut B, boot: &BootSector) {
+        for sector in self.sectors.iter_mut().filter(|sector| sector.dirty) {
+            write_copies(device, boot, sector.index, &sector.data);
+            sector.dirty = false;
+        }
+    }
+
+    /// Flush, then keep at most `capacity` sectors from now on.
+    pub(crate) fn resize<B: BlockDevice>(&mut self, device: &mut B, boot: &BootSector, capacity: usize) {
+        self.flush(device, boot);
+        self.sectors.clear();
+        self.capacity = capacity;
+    }
+
+    /// Drop the least recently used sector, writing it back if dirty.
+    fn evict<B: BlockDevice>(&mut self, device: &mut B, boot: &BootSector) {
+        let Some(oldest) = (0..self.sectors.len()).min_by_key(|&i| self.sectors[i].last_use) else {
+            return;
+        };
+        let sector = self.sectors.swap_remove(oldest);
+        if sector.dirty {
+            write_copies(device, boot, sector.index, &sector.data);
+        }
+    }
+}
+
+/// Write sector `index` of the FAT to every copy.
+fn write_copies<B: BlockDevice>(device: &mut B, boot: &BootSector, index: u64, data: &[u8; 512]) {
+    for fat_copy in 0..boot.fat_count as u64 {
+        let fat_base = boot.reserved_sectors as u64 + fat_copy * boot.fat_size_sectors as u64;
+        device.write_sector(fat_base + index, data);
+    }
+}
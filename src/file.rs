@@ -0,0 +1,142 @@
+use crate::{
+    block::BlockDevice,
+    directory::DirEntry,
+    error::FatError,
+    time::{NoTimeSource, TimeSource},
+    volume::Fat32Volume,
+};
+
+/// Position to seek to, relative to the start, end or current position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u32),
+    End(i64),
+    Current(i64),
+}
+
+/// Byte position within a cluster chain, remembering the last cluster
+/// reached so sequential access does not walk the chain from the start.
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor {
+    pub(crate) first_cluster: u32,
+    pub(crate) size: u32,
+    pub(crate) pos: u32,
+    /// Index in the chain and number of the last cluster looked up.
+    cached: Option<(u32, u32)>,
+}
+
+impl Cursor {
+    pub(crate) fn new(first_cluster: u32, size: u32) -> Self {
+        Self { first_cluster, size, pos: 0, cached: None }
+    }
+
+    /// Cluster holding the `index`-th cluster-sized block of the file.
+    pub(crate) fn cluster_at<B: BlockDevice, T: TimeSource>(
+        &mut self,
+        volume: &Fat32Volume<B, T>,
+        index: u32,
+    ) -> Result<u32, FatError> {
+        let (start_index, start_cluster) = match self.cached {
+            Some((cached_index, cluster)) if cached_index == index => return Ok(cluster),
+            Some((cached_index, cluster)) if cached_index < index => (cached_index, cluster),
+            _ => (0, self.first_cluster),
+        };
+
+        let cluster = volume
+            .cluster_chain(start_cluster)
+            .nth((index - start_index) as usize)
+            .ok_or(FatError::ChainTooShort)??;
+        self.cached = Some((index, cluster));
+        Ok(cluster)
+    }
+
+    /// Compute the position designated by `from`.
+    pub(crate) fn seek_target(&self, from: SeekFrom) -> Result<u32, FatError> {
+        let target = match from {
+            SeekFrom::Start(offset) => return Ok(offset),
+            SeekFrom::End(delta) => self.size as i64 + delta,
+            SeekFrom::Current(delta) => self.pos as i64 + delta,
+        };
+        u32::try_from(target).map_err(|_| FatError::InvalidSeek)
+    }
+
+    /// Read up to `buf.len()` bytes from the current position.
+    pub(crate) fn read<B: BlockDevice, T: TimeSource>(
+        &mut self,
+        volume: &Fat32Volume<B, T>,
+        buf: &mut [u8],
+    ) -> Result<usize, FatError> {
+        let cluster_size = volume.cluster_size();
+        let mut sector = [0u8; 512];
+        let mut done = 0;
+
+        while done < buf.len() && self.pos < self.size {
+            let pos = self.pos;
+            let cluster = self.cluster_at(volume, pos / cluster_size)?;
+            let in_cluster = pos % cluster_size;
+            let lba = volume.cluster_lba(cluster) + (in_cluster / 512) as u64;
+            let in_sector = (in_cluster % 512) as usize;
+
+            let n = (512 - in_sector)
+                .min((self.size - pos) as usize)
+                .min(buf.len() - done);
+
+            if n == 512 {
+                volume.device.read_sector(lba, &mut buf[done..done + 512]);
+            } else {
+                volume.device.read_sector(lba, &mut sector);
+                buf[done..done + n].copy_from_slice(&sector[in_sector..in_sector + n]);
+            }
+
+            done += n;
+            self.pos += n as u32;
+        }
+
+        Ok(done)
+    }
+}
+
+/// Read-only handle on the contents of a file.
+pub struct File<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    volume: &'a Fat32Volume<B, T>,
+    cursor: Cursor,
+}
+
+impl<'a, B: BlockDevice, T: TimeSource> File<'a, B, T> {
+    /// Open the file described by `entry`.
+    pub fn new(volume: &'a Fat32Volume<B, T>, entry: &DirEntry) -> Result<Self, FatError> {
+        if entry.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+        Ok(Self { volume, cursor: Cursor::new(entry.first_cluster, entry.size) })
+    }
+
+    /// File size in bytes.
+    pub fn len(&self) -> u32 {
+        self.cursor.size
+    }
+
+    /// Whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.cursor.size == 0
+    }
+
+    /// Current read position.
+    pub fn position(&self) -> u32 {
+        self.cursor.pos
+    }
+
+    /// Move the read position. Seeking past the end is allowed; reads
+    /// there return 0 bytes.
+    pub fn seek(&mut self, from: SeekFrom) -> Result<u32, FatError> {
+        self.cursor.pos = self.cursor.seek_target(from)?;
+        Ok(self.cursor.pos)
+    }
+
+    /// Read up to `buf.len()` bytes from the current position.
+    ///
+    /// Returns the number of bytes read, 0 at end of file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, FatError> {
+        self.cursor.read(self.volume, buf)
+    }
+}
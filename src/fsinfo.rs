@@ -0,0 +1,56 @@
+use crate::error::FatError;
+
+/// Signature at offset 0 of the FSInfo sector.
+pub const FSINFO_LEAD_SIG: u32 = 0x4161_5252;
+/// Signature at offset 484 of the FSInfo sector.
+pub const FSINFO_STRUCT_SIG: u32 = 0x6141_7272;
+/// Signature at offset 508 of the FSInfo sector.
+pub const FSINFO_TRAIL_SIG: u32 = 0xAA55_0000;
+/// Value of the free count and next-free fields when not known.
+pub const FSINFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+/// FAT32 FSInfo sector: allocation hints maintained by the driver.
+///
+/// Both fields are only hints; the FAT is authoritative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsInfo {
+    /// Last known number of free clusters
+    pub free_count: Option<u32>,
+    /// Cluster from which to start looking for free clusters
+    pub next_free: Option<u32>,
+}
+
+impl FsInfo {
+    /// Parse an FSInfo sector, checking its three signatures.
+    pub fn parse(sector: &[u8]) -> Result<Self, FatError> {
+        if sector.len() < 512 {
+            return Err(FatError::InvalidFsInfo);
+        }
+
+        let field = |offset: usize| {
+            u32::from_le_bytes([sector[offset], sector[offset + 1], sector[offset + 2], sector[offset + 3]])
+        };
+
+        if field(0) != FSINFO_LEAD_SIG
+            || field(484) != FSINFO_STRUCT_SIG
+            || field(508) != FSINFO_TRAIL_SIG
+        {
+            return Err(FatError::InvalidFsInfo);
+        }
+
+        let known = |value: u32| (value != FSINFO_UNKNOWN).then_some(value);
+        Ok(Self {
+            free_count: known(field(488)),
+            next_free: known(field(492)),
+        })
+    }
+
+    /// Store the free count and next-free hint into an FSInfo sector,
+    /// leaving the signatures and reserved areas untouched.
+    pub fn store(&self, sector: &mut [u8]) {
+        let free_count = self.free_count.unwrap_or(FSINFO_UNKNOWN);
+        let next_free = self.next_free.unwrap_or(FSINFO_UNKNOWN);
+        sector[488..492].copy_from_slice(&free_count.to_le_bytes());
+        sector[492..496].copy_from_slice(&next_free.to_le_bytes());
+    }
+}
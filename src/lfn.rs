@@ -0,0 +1,164 @@
+use core::fmt;
+
+/// Maximum length of a long file name in UTF-16 code units.
+pub const LFN_MAX_LEN: usize = 255;
+/// Number of UTF-16 code units stored in one LFN slot.
+pub const LFN_CHARS_PER_SLOT: usize = 13;
+/// Flag set in the sequence number of the last (physically first) slot.
+pub const LFN_LAST_SLOT: u8 = 0x40;
+
+/// Byte offsets of the 13 UTF-16 code units inside an LFN slot.
+const LFN_CHAR_OFFSETS: [usize; LFN_CHARS_PER_SLOT] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Checksum of an 11-byte short name, stored in each of its LFN slots.
+pub fn checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, &b| ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(b))
+}
+
+/// Number of LFN slots needed to store a name of `len` UTF-16 code units.
+pub fn slots_needed(len: usize) -> usize {
+    len.div_ceil(LFN_CHARS_PER_SLOT)
+}
+
+/// Build the LFN slot with sequence number `seq` (1-based) for `units`.
+///
+/// The name is terminated by a 0x0000 unit when it does not fill the last
+/// slot, and the rest of that slot is padded with 0xFFFF.
+pub fn encode_slot(units: &[u16], seq: u8, checksum: u8) -> [u8; 32] {
+    let mut slot = [0u8; 32];
+    let last = seq as usize == slots_needed(units.len());
+    slot[0] = if last { seq | LFN_LAST_SLOT } else { seq };
+    slot[11] = crate::directory::ATTR_LONG_NAME;
+    slot[13] = checksum;
+
+    let base = (seq as usize - 1) * LFN_CHARS_PER_SLOT;
+    for (i, &off) in LFN_CHAR_OFFSETS.iter().enumerate() {
+        let index = base + i;
+        let unit = match index.cmp(&units.len()) {
+            core::cmp::Ordering::Less => units[index],
+            core::cmp::Ordering::Equal => 0x0000,
+            core::cmp::Ordering::Greater => 0xFFFF,
+        };
+        slot[off..off + 2].copy_from_slice(&unit.to_le_bytes());
+    }
+    slot
+}
+
+/// A VFAT long file name, kept as UTF-16 without allocating.
+#[derive(Clone)]
+pub struct LongName {
+    units: [u16; LFN_MAX_LEN],
+    len: usize,
+}
+
+impl LongName {
+    /// The name as UTF-16 code units.
+    pub fn as_utf16(&self) -> &[u16] {
+        &self.units[..self.len]
+    }
+
+    /// The name as characters; unpaired surrogates become U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        char::decode_utf16(self.as_utf16().iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+impl fmt::Display for LongName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl fmt::Debug for LongName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+/// Accumulates LFN slots read in directory order until the short entry
+/// they belong to is reached.
+///
+/// Slots are stored on disk in reverse order: the one flagged with
+/// [`LFN_LAST_SLOT`] comes first and carries the highest sequence number,
+/// down to sequence number 1 right before the short entry.
+pub(crate) struct LfnAccumulator {
+    units: [u16; LFN_MAX_LEN],
+    checksum: u8,
+    slots: u8,
+    /// Sequence number expected in the next slot; 0 once complete.
+    expected: u8,
+    active: bool,
+}
+
+impl LfnAccumulator {
+    pub(crate) fn new() -> Self {
+        Self {
+            units: [0u16; LFN_MAX_LEN],
+            checksum: 0,
+            slots: 0,
+            expected: 0,
+            active: false,
+        }
+    }
+
+    /// Drop any partially collected name.
+    pub(crate) fn reset(&mut self) {
+        self.active = false;
+    }
+
+    /// Number of slots of the last name started.
+    pub(crate) fn slots(&self) -> u8 {
+        self.slots
+    }
+
+    /// Feed one LFN slot. Out-of-sequence slots orphan the pending name.
+    pub(crate) fn push(&mut self, slot: &[u8]) {
+        let order = slot[0];
+        let seq = order & 0x1F;
+
+        if order & LFN_LAST_SLOT != 0 {
+            if seq == 0 || seq as usize > LFN_MAX_LEN.div_ceil(LFN_CHARS_PER_SLOT) {
+                self.active = false;
+                return;
+            }
+            self.active = true;
+            self.checksum = slot[13];
+            self.slots = seq;
+            self.units = [0xFFFF; LFN_MAX_LEN];
+        } else if !self.active || seq == 0 || seq != self.expected || slot[13] != self.checksum {
+            self.active = false;
+            return;
+        }
+
+        let base = (seq as usize - 1) * LFN_CHARS_PER_SLOT;
+        for (i, &off) in LFN_CHAR_OFFSETS.iter().enumerate() {
+            if base + i < LFN_MAX_LEN {
+                self.units[base + i] = u16::from_le_bytes([slot[off], slot[off + 1]]);
+            }
+        }
+        self.expected = seq - 1;
+    }
+
+    /// Complete the pending name against the short entry that follows it.
+    ///
+    /// Returns `None` when no complete chain precedes the entry or when the
+    /// chain's checksum does not match the short name.
+    pub(crate) fn finish(&mut self, short_name: &[u8; 11]) -> Option<LongName> {
+        let complete = self.active && self.expected == 0;
+        self.active = false;
+        if !complete || checksum(short_name) != self.checksum {
+            return None;
+        }
+
+        let max = (self.slots as usize * LFN_CHARS_PER_SLOT).min(LFN_MAX_LEN);
+        let len = self.units[..max].iter().position(|&u| u == 0).unwrap_or(max);
+        if len == 0 {
+            return None;
+        }
+        Some(LongName { units: self.units, len })
+    }
+}
@@ -1,12 +1,24 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+pub mod allocator;
 pub mod block;
 pub mod boot_sector;
 pub mod volume;
 pub mod directory;
 pub mod error;
+pub mod fat;
+#[cfg(feature = "alloc")]
+mod fat_cache;
+pub mod file;
+pub mod fsinfo;
+pub mod lfn;
+pub mod name;
+pub mod time;
+#[cfg(feature = "alloc")]
 pub mod write;
 
 #[cfg(test)]
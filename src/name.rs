@@ -0,0 +1,170 @@
+use crate::{directory::trim_padding, error::FatError, lfn::LFN_MAX_LEN};
+
+/// Characters allowed in short names besides `A`-`Z` and `0`-`9`.
+const SHORT_NAME_SPECIALS: &[u8] = b"$%'-_@~`!(){}^#&";
+/// Characters never allowed in a long name.
+const INVALID_LONG_NAME_CHARS: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+/// Plain `~N` tails tried before switching to hashed tails, as Windows does.
+const PLAIN_TAILS_FIRST: u32 = 4;
+
+/// Strip the trailing spaces and periods Windows ignores and check that
+/// what remains is a legal long name.
+pub fn normalize_long_name(name: &str) -> Result<&str, FatError> {
+    let name = name.trim_end_matches([' ', '.']);
+    let illegal = name
+        .chars()
+        .any(|c| (c as u32) < 0x20 || INVALID_LONG_NAME_CHARS.contains(&c));
+
+    if name.is_empty() || illegal || name.encode_utf16().count() > LFN_MAX_LEN {
+        return Err(FatError::InvalidFileName);
+    }
+    Ok(name)
+}
+
+/// Short name chosen for a long name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShortName {
+    /// Space-padded 8.3 name as stored on disk
+    pub name: [u8; 11],
+    /// Whether LFN slots are needed to preserve the long name
+    pub needs_lfn: bool,
+}
+
+/// Derive the short name for `long_name`, which must already be
+/// normalized.
+///
+/// A name that converts to 8.3 without loss is used as is. Otherwise a
+/// numeric tail is appended: `~1` to `~4` first, then a hash of the long
+/// name followed by `~1` to `~9`, so large directories do not probe
+/// thousands of candidates, and finally plain tails again. `exists` is
+/// asked about each candidate and the first unused one is returned.
+pub fn generate_short_name(
+    long_name: &str,
+    mut exists: impl FnMut(&[u8; 11]) -> bool,
+) -> Result<ShortName, FatError> {
+    let (basis, lossy) = basis_name(long_name);
+    let needs_lfn = lossy || !short_name_eq(&basis, long_name);
+
+    if !lossy {
+        return Ok(ShortName { name: basis, needs_lfn });
+    }
+
+    let hash = name_hash(long_name);
+    let plain = (1..=PLAIN_TAILS_FIRST).map(|n| with_tail(&basis, None, n));
+    let hashed = (1..=9).map(|n| with_tail(&basis, Some(hash), n));
+    let rest = (PLAIN_TAILS_FIRST + 1..=999_999).map(|n| with_tail(&basis, None, n));
+
+    plain
+        .chain(hashed)
+        .chain(rest)
+        .find(|candidate| !exists(candidate))
+        .map(|name| ShortName { name, needs_lfn })
+        .ok_or(FatError::NoFreeShortName)
+}
+
+/// Basis name per the VFAT rules, and whether producing it lost
+/// information (invalid characters, stripped spaces or periods,
+/// truncation).
+fn basis_name(long_name: &str) -> ([u8; 11], bool) {
+    let mut name = [b' '; 11];
+    let mut lossy = false;
+
+    let stripped = long_name.trim_start_matches('.');
+    lossy |= stripped.len() != long_name.len();
+
+    let (base, ext) = match stripped.rfind('.') {
+        Some(dot) => (&stripped[..dot], Some(&stripped[dot + 1..])),
+        None => (stripped, None),
+    };
+
+    lossy |= fill_part(&mut name[..8], base);
+    if let Some(ext) = ext {
+        lossy |= fill_part(&mut name[8..], ext);
+    }
+
+    if name[0] == b' ' {
+        name[0] = b'_';
+        lossy = true;
+    }
+    (name, lossy)
+}
+
+/// Copy the converted characters of `part` into `field`, returning
+/// whether anything was replaced, dropped or truncated.
+fn fill_part(field: &mut [u8], part: &str) -> bool {
+    let mut lossy = false;
+    let mut len = 0;
+
+    for c in part.chars() {
+        if c == ' ' || c == '.' {
+            lossy = true;
+            continue;
+        }
+        if len == field.len() {
+            return true;
+        }
+
+        let upper = c.to_ascii_uppercase();
+        let valid = upper.is_ascii()
+            && (upper.is_ascii_alphanumeric() || SHORT_NAME_SPECIALS.contains(&(upper as u8)));
+        field[len] = if valid {
+            upper as u8
+        } else {
+            lossy = true;
+            b'_'
+        };
+        len += 1;
+    }
+    lossy
+}
+
+/// Whether `name` spells exactly the 8.3 name `short`, including case.
+fn short_name_eq(short: &[u8; 11], name: &str) -> bool {
+    let base = trim_padding(&short[..8]);
+    let ext = trim_padding(&short[8..]);
+    let mut expected = [0u8; 12];
+    let mut len = base.len();
+    expected[..len].copy_from_slice(base);
+    if !ext.is_empty() {
+        expected[len] = b'.';
+        expected[len + 1..len + 1 + ext.len()].copy_from_slice(ext);
+        len += 1 + ext.len();
+    }
+    name.as_bytes() == &expected[..len]
+}
+
+/// 16-bit hash of a long name, used for hashed tails.
+fn name_hash(long_name: &str) -> u16 {
+    long_name
+        .encode_utf16()
+        .fold(0u16, |hash, unit| hash.rotate_right(1).wrapping_add(unit))
+}
+
+/// Apply a `~N` tail, optionally preceded by four hex digits of `hash`
+/// which then replace all but the first two characters of the base.
+fn with_tail(basis: &[u8; 11], hash: Option<u16>, n: u32) -> [u8; 11] {
+    let mut tail = [0u8; 11];
+    let mut tail_len = 0;
+
+    if let Some(hash) = hash {
+        for shift in [12, 8, 4, 0] {
+            tail[tail_len] = b"0123456789ABCDEF"[(hash >> shift) as usize & 0xF];
+            tail_len += 1;
+        }
+    }
+    tail[tail_len] = b'~';
+    tail_len += 1;
+    let digits = n.checked_ilog10().unwrap_or(0) as usize + 1;
+    for i in 0..digits {
+        tail[tail_len + digits - 1 - i] = b'0' + (n / 10u32.pow(i as u32) % 10) as u8;
+    }
+    tail_len += digits;
+
+    let base_len = trim_padding(&basis[..8]).len();
+    let keep = if hash.is_some() { base_len.min(2) } else { base_len.min(8 - tail_len) };
+
+    let mut name = *basis;
+    name[keep..keep + tail_len].copy_from_slice(&tail[..tail_len]);
+    name[keep + tail_len..8].fill(b' ');
+    name
+}
@@ -0,0 +1,86 @@
+/// Date and time as stored in FAT directory entries.
+///
+/// FAT dates cover 1980 to 2107. Times have a two-second resolution,
+/// except for the creation time whose extra byte adds 10 ms steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millis: u16,
+}
+
+/// 1980-01-01 00:00:00, the earliest date FAT can represent.
+pub const FAT_EPOCH: DateTime = DateTime {
+    year: 1980,
+    month: 1,
+    day: 1,
+    hour: 0,
+    minute: 0,
+    second: 0,
+    millis: 0,
+};
+
+impl DateTime {
+    /// Decode a FAT date, time and creation-time tenths byte.
+    ///
+    /// Pass 0 for `time` and `tenths` when only a date is stored, as for
+    /// the last access date.
+    pub fn from_fat(date: u16, time: u16, tenths: u8) -> Self {
+        let tenths = tenths.min(199);
+        Self {
+            year: 1980 + (date >> 9),
+            month: ((date >> 5) & 0x0F) as u8,
+            day: (date & 0x1F) as u8,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3F) as u8,
+            second: (time & 0x1F) as u8 * 2 + tenths / 100,
+            millis: (tenths % 100) as u16 * 10,
+        }
+    }
+
+    /// Encode the date part. Years outside 1980..=2107 are clamped.
+    pub fn fat_date(&self) -> u16 {
+        let year = self.year.clamp(1980, 2107) - 1980;
+        (year << 9) | ((self.month as u16 & 0x0F) << 5) | (self.day as u16 & 0x1F)
+    }
+
+    /// Encode the time part, with two-second resolution.
+    pub fn fat_time(&self) -> u16 {
+        ((self.hour as u16 & 0x1F) << 11)
+            | ((self.minute as u16 & 0x3F) << 5)
+            | ((self.second as u16 / 2) & 0x1F)
+    }
+
+    /// Encode the creation-time tenths byte: the odd second and the
+    /// milliseconds, in units of 10 ms (0..=199).
+    pub fn fat_tenths(&self) -> u8 {
+        (self.second % 2) * 100 + (self.millis.min(999) / 10) as u8
+    }
+}
+
+/// Source of the current time for stamping directory entries.
+pub trait TimeSource {
+    /// The current local date and time.
+    fn now(&self) -> DateTime;
+}
+
+impl<C: TimeSource + ?Sized> TimeSource for &C {
+    fn now(&self) -> DateTime {
+        (**self).now()
+    }
+}
+
+/// Time source of volumes opened without one: every entry is stamped
+/// [`FAT_EPOCH`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTimeSource;
+
+impl TimeSource for NoTimeSource {
+    fn now(&self) -> DateTime {
+        FAT_EPOCH
+    }
+}
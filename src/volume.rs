@@ -1,19 +1,206 @@
-use crate::{block::BlockDevice, boot_sector::BootSector, error::FatError};
+#[cfg(feature = "alloc")]
+use crate::{
+    allocator::{AllocationStrategy, FreeBitmap},
+    fat::FatTableMut,
+    fat_cache::{FatCache, DEFAULT_FAT_CACHE_SECTORS},
+};
+use crate::{
+    block::BlockDevice,
+    boot_sector::BootSector,
+    directory::{DirEntry, DirIter, ATTR_DIRECTORY, ATTR_LONG_NAME, ATTR_VOLUME_ID, DOTDOT_NAME, DOT_NAME, ENTRY_DELETED},
+    error::FatError,
+    fat::{ClusterChain, FatEntry, FatTable, ENTRIES_PER_SECTOR},
+    file::File,
+    fsinfo::FsInfo,
+    time::{DateTime, NoTimeSource, TimeSource},
+};
+
+/// Where the free cluster count of a [`SpaceInfo`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreeCountSource {
+    /// Counted by scanning every FAT entry.
+    FatScan,
+    /// Taken from the FSInfo sector without scanning.
+    FsInfo,
+}
+
+/// Space usage of a volume, in the spirit of `statfs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpaceInfo {
+    /// Data clusters on the volume
+    pub total_clusters: u32,
+    /// Clusters available for allocation
+    pub free_clusters: u32,
+    /// Clusters marked bad; unknown unless the FAT was scanned
+    pub bad_clusters: Option<u32>,
+    /// Cluster size in bytes
+    pub cluster_size: u32,
+    /// How `free_clusters` was obtained
+    pub source: FreeCountSource,
+}
+
+impl SpaceInfo {
+    /// Capacity of the data region in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.total_clusters as u64 * self.cluster_size as u64
+    }
+
+    /// Free space in bytes.
+    pub fn free_bytes(&self) -> u64 {
+        self.free_clusters as u64 * self.cluster_size as u64
+    }
+
+    /// Space taken by files, directories and bad clusters, in bytes.
+    pub fn used_bytes(&self) -> u64 {
+        self.total_bytes() - self.free_bytes()
+    }
+}
 
 /// FAT32 volume representation
-pub struct Fat32Volume<B: BlockDevice> {
+///
+/// `T` supplies the time for stamping entries. It is owned by the volume,
+/// so a clock driver can be moved in, or borrowed by passing a reference.
+pub struct Fat32Volume<B: BlockDevice, T: TimeSource = NoTimeSource> {
     pub boot: BootSector,
     pub(crate) device: B,
+    pub(crate) time_source: T,
+    pub(crate) ignore_read_only: bool,
+    /// FSInfo hints as maintained since the volume was opened; `None` if
+    /// the sector is missing or invalid.
+    fsinfo: Option<FsInfo>,
+    /// Whether `fsinfo` differs from the sector on disk.
+    fsinfo_dirty: bool,
+    /// Free cluster bitmap, built on the first allocation once enabled.
+    #[cfg(feature = "alloc")]
+    pub(crate) free_bitmap: Option<FreeBitmap>,
+    /// Largest free bitmap allowed, in bytes; 0 disables it.
+    #[cfg(feature = "alloc")]
+    pub(crate) free_bitmap_limit: usize,
+    /// FAT sectors modified but not yet written out.
+    #[cfg(feature = "alloc")]
+    fat_cache: FatCache,
+    /// Strategy for allocations not made through a file handle.
+    #[cfg(feature = "alloc")]
+    pub(crate) allocation_strategy: AllocationStrategy,
+    /// Where next-fit allocation resumes; the FSInfo hint at first.
+    #[cfg(feature = "alloc")]
+    pub(crate) next_free_hint: Option<u32>,
 }
 
 impl<B: BlockDevice> Fat32Volume<B> {
-    /// Open a FAT32 volume from a block device.
+    /// Open a FAT32 volume from a block device. Entries are stamped
+    /// 1980-01-01 00:00:00; use [`open_with_time_source`](Self::open_with_time_source)
+    /// to record real times.
     pub fn open(device: B) -> Result<Self, FatError> {
+        Self::open_with_time_source(device, NoTimeSource)
+    }
+}
+
+impl<B: BlockDevice, T: TimeSource> Fat32Volume<B, T> {
+    /// Open a FAT32 volume from a block device, timestamping entries
+    /// created or modified with `time_source`.
+    pub fn open_with_time_source(device: B, time_source: T) -> Result<Self, FatError> {
         let mut sector = [0u8; 512];
         device.read_sector(0, &mut sector);
 
         let boot = BootSector::parse(&sector)?;
-        Ok(Self { boot, device })
+        let fsinfo = Self::read_fsinfo(&device, &boot).ok();
+        Ok(Self {
+            boot,
+            device,
+            time_source,
+            ignore_read_only: false,
+            fsinfo,
+            fsinfo_dirty: false,
+            #[cfg(feature = "alloc")]
+            free_bitmap: None,
+            #[cfg(feature = "alloc")]
+            free_bitmap_limit: 0,
+            #[cfg(feature = "alloc")]
+            fat_cache: FatCache::new(DEFAULT_FAT_CACHE_SECTORS),
+            #[cfg(feature = "alloc")]
+            allocation_strategy: AllocationStrategy::default(),
+            #[cfg(feature = "alloc")]
+            next_free_hint: fsinfo.and_then(|info| info.next_free),
+        })
+    }
+
+    /// Write back everything kept in memory: cached FAT sectors, to every
+    /// FAT copy, then the FSInfo hints. Until then the FSInfo sector may
+    /// be stale, which readers tolerate since it only holds hints.
+    pub fn sync(&mut self) -> Result<(), FatError> {
+        #[cfg(feature = "alloc")]
+        self.flush_fat();
+
+        if let (Some(info), true) = (self.fsinfo, self.fsinfo_dirty) {
+            let lba = self.boot.fsinfo_sector as u64;
+            let mut sector = [0u8; 512];
+            self.device.read_sector(lba, &mut sector);
+            FsInfo::parse(&sector)?;
+            info.store(&mut sector);
+            self.device.write_sector(lba, &sector);
+            self.fsinfo_dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Sync the volume and hand back the block device.
+    pub fn unmount(mut self) -> Result<B, FatError> {
+        self.sync()?;
+        Ok(self.device)
+    }
+
+    /// The underlying block device.
+    pub fn device(&self) -> &B {
+        &self.device
+    }
+
+    /// Use `source` to timestamp entries created or modified from now on.
+    pub fn set_time_source(&mut self, source: T) {
+        self.time_source = source;
+    }
+
+    /// Let mutating operations modify and delete entries marked read-only
+    /// instead of failing with [`FatError::ReadOnly`].
+    pub fn set_ignore_read_only(&mut self, ignore: bool) {
+        self.ignore_read_only = ignore;
+    }
+
+    /// Keep an in-memory bitmap of free clusters, one bit per cluster, if
+    /// it takes at most `max_bytes`; 0 disables it.
+    ///
+    /// The bitmap is built from the FAT on the next allocation and lets
+    /// allocations skip the FAT scan. Larger volumes keep scanning.
+    #[cfg(feature = "alloc")]
+    pub fn set_free_bitmap_limit(&mut self, max_bytes: usize) {
+        self.free_bitmap_limit = max_bytes;
+        if FreeBitmap::size_for(self.cluster_count()) > max_bytes {
+            self.free_bitmap = None;
+        }
+    }
+
+    /// Cache up to `sectors` modified FAT sectors (8 by default). With 0,
+    /// FAT changes are written through at once.
+    ///
+    /// Cached sectors are written out before any directory entry that
+    /// could point into them and after chains are freed, so the device
+    /// never holds an entry whose chain is missing. Growth of a file
+    /// through an open handle stays cached until the handle is flushed.
+    #[cfg(feature = "alloc")]
+    pub fn set_fat_cache_size(&mut self, sectors: usize) {
+        self.fat_cache.resize(&mut self.device, &self.boot, sectors);
+    }
+
+    /// Choose how clusters are allocated (next-fit by default). File
+    /// handles can override it through their open options.
+    #[cfg(feature = "alloc")]
+    pub fn set_allocation_strategy(&mut self, strategy: AllocationStrategy) {
+        self.allocation_strategy = strategy;
+    }
+
+    /// Current time according to the configured time source.
+    pub fn now(&self) -> DateTime {
+        self.time_source.now()
     }
 
     /// Volume size in bytes.
@@ -35,4 +222,345 @@ impl<B: BlockDevice> Fat32Volume<B> {
     pub fn root_cluster(&self) -> u32 {
         self.boot.root_cluster
     }
+
+    /// OEM name recorded by the formatting tool.
+    pub fn oem_name(&self) -> &[u8; 8] {
+        &self.boot.oem_name
+    }
+
+    /// Media descriptor byte.
+    pub fn media_descriptor(&self) -> u8 {
+        self.boot.media_descriptor
+    }
+
+    /// Raw FAT mirroring flags.
+    pub fn ext_flags(&self) -> u16 {
+        self.boot.ext_flags
+    }
+
+    /// Filesystem version as `(major, minor)`.
+    pub fn fs_version(&self) -> (u8, u8) {
+        ((self.boot.fs_version >> 8) as u8, self.boot.fs_version as u8)
+    }
+
+    /// Sector number of the FSInfo structure.
+    pub fn fsinfo_sector(&self) -> u16 {
+        self.boot.fsinfo_sector
+    }
+
+    /// Sector number of the backup boot sector.
+    pub fn backup_boot_sector(&self) -> u16 {
+        self.boot.backup_boot_sector
+    }
+
+    /// Volume serial number.
+    pub fn volume_serial(&self) -> Option<u32> {
+        self.boot.volume_id
+    }
+
+    /// Filesystem type string from the boot sector, e.g. `FAT32   `.
+    pub fn fs_type(&self) -> Option<&[u8; 8]> {
+        self.boot.fs_type.as_ref()
+    }
+
+    /// Volume label stored in the root directory's volume ID entry.
+    pub fn root_volume_label(&self) -> Result<Option<[u8; 11]>, FatError> {
+        let mut slots = DirIter::new(self, self.root_cluster());
+        while let Some(slot) = slots.next_slot() {
+            let (_, slot) = slot?;
+            let attributes = slot[11];
+            if slot[0] == ENTRY_DELETED || attributes & ATTR_LONG_NAME == ATTR_LONG_NAME {
+                continue;
+            }
+            if attributes & (ATTR_VOLUME_ID | ATTR_DIRECTORY) == ATTR_VOLUME_ID {
+                let mut label = [0u8; 11];
+                label.copy_from_slice(&slot[..11]);
+                return Ok(Some(label));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Volume label, space padded.
+    ///
+    /// The root directory entry wins over the boot sector copy, as on
+    /// Windows, which only updates the former when relabelling.
+    pub fn volume_label(&self) -> Result<Option<[u8; 11]>, FatError> {
+        Ok(self.root_volume_label()?.or(self.boot.volume_label))
+    }
+
+    /// FSInfo hints: as read from disk when the volume was opened, kept up
+    /// to date by allocations since and written back by [`sync`](Self::sync).
+    pub fn fsinfo(&self) -> Result<FsInfo, FatError> {
+        self.fsinfo.ok_or(FatError::InvalidFsInfo)
+    }
+
+    /// Update the in-memory FSInfo hints, if the volume has any. A free
+    /// count that stops being plausible becomes unknown.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn update_fsinfo(&mut self, update: impl FnOnce(&mut FsInfo)) {
+        let cluster_count = self.cluster_count();
+        if let Some(info) = &mut self.fsinfo {
+            update(info);
+            info.free_count = info.free_count.filter(|&free| free <= cluster_count);
+            self.fsinfo_dirty = true;
+        }
+    }
+
+    /// Read and validate the FSInfo sector.
+    fn read_fsinfo(device: &B, boot: &BootSector) -> Result<FsInfo, FatError> {
+        let lba = boot.fsinfo_sector;
+        if lba == 0 || lba == 0xFFFF {
+            return Err(FatError::InvalidFsInfo);
+        }
+
+        let mut sector = [0u8; 512];
+        device.read_sector(lba as u64, &mut sector);
+        FsInfo::parse(&sector)
+    }
+
+    /// Report total, free and bad clusters.
+    ///
+    /// With `trust_fsinfo`, the free count recorded in FSInfo is used when
+    /// it is valid and plausible, avoiding a scan of the whole FAT; the
+    /// bad cluster count is then unknown. Otherwise every FAT entry is read.
+    pub fn space_info(&self, trust_fsinfo: bool) -> Result<SpaceInfo, FatError> {
+        let total_clusters = self.cluster_count();
+        let cluster_size = self.cluster_size();
+
+        if trust_fsinfo {
+            if let Ok(FsInfo { free_count: Some(free), .. }) = self.fsinfo() {
+                if free <= total_clusters {
+                    return Ok(SpaceInfo {
+                        total_clusters,
+                        free_clusters: free,
+                        bad_clusters: None,
+                        cluster_size,
+                        source: FreeCountSource::FsInfo,
+                    });
+                }
+            }
+        }
+
+        let mut free = 0;
+        let mut bad = 0;
+        let mut sector = [0u8; 512];
+        let end = total_clusters as u64 + 2;
+        let mut cluster = 2u64;
+        let per_sector = ENTRIES_PER_SECTOR as u64;
+        while cluster < end {
+            self.read_fat_sector(cluster / per_sector, &mut sector);
+            let last = end.min((cluster / per_sector + 1) * per_sector);
+            for c in cluster..last {
+                match FatEntry::in_sector(&sector, c as u32) {
+                    FatEntry::Free => free += 1,
+                    FatEntry::Bad => bad += 1,
+                    _ => {}
+                }
+            }
+            cluster = last;
+        }
+
+        Ok(SpaceInfo {
+            total_clusters,
+            free_clusters: free,
+            bad_clusters: Some(bad),
+            cluster_size,
+            source: FreeCountSource::FatScan,
+        })
+    }
+
+    /// First sector of the data region.
+    pub fn first_data_sector(&self) -> u64 {
+        self.boot.first_data_sector()
+    }
+
+    /// Number of data clusters.
+    pub fn cluster_count(&self) -> u32 {
+        self.boot.cluster_count()
+    }
+
+    /// Read access to the FAT entries of the data clusters.
+    pub fn fat(&self) -> FatTable<'_, B, T> {
+        FatTable::new(self)
+    }
+
+    /// Read and write access to the FAT entries of the data clusters.
+    /// Changes reach the device on [`sync`](Self::sync).
+    #[cfg(feature = "alloc")]
+    pub fn fat_mut(&mut self) -> FatTableMut<'_, B, T> {
+        FatTableMut::new(self)
+    }
+
+    /// Iterate over the cluster chain starting at `first_cluster`.
+    pub fn cluster_chain(&self, first_cluster: u32) -> ClusterChain<'_, B, T> {
+        ClusterChain::new(self, first_cluster)
+    }
+
+    /// Iterate over the entries of the directory starting at `dir_cluster`.
+    ///
+    /// Cluster 0, as stored in the `..` entry of top-level directories,
+    /// refers to the root directory.
+    pub fn read_dir(&self, dir_cluster: u32) -> DirIter<'_, B, T> {
+        DirIter::new(self, dir_cluster)
+    }
+
+    /// Find the entry called `name` in the directory at `dir_cluster`.
+    ///
+    /// Both long and short names are compared, ignoring case.
+    pub fn find_entry(&self, dir_cluster: u32, name: &str) -> Result<DirEntry, FatError> {
+        for entry in self.read_dir(dir_cluster) {
+            let entry = entry?;
+            if entry.matches(name) {
+                return Ok(entry);
+            }
+        }
+        Err(FatError::NotFound)
+    }
+
+    /// Resolve `path` from the root directory.
+    ///
+    /// Components may be separated by `/` or `\`; `.` and `..` are
+    /// followed through the directory's own entries. The root itself is
+    /// returned as a synthesized directory entry with a blank name.
+    pub fn open_path(&self, path: &str) -> Result<DirEntry, FatError> {
+        let root = self.root_cluster();
+        let mut current = DirEntry::root(root);
+
+        for component in path.split(['/', '\\']).filter(|c| !c.is_empty()) {
+            if !current.is_dir() {
+                return Err(FatError::NotADirectory);
+            }
+
+            match component {
+                "." => {}
+                ".." => {
+                    if current.first_cluster != root {
+                        current = self.parent_entry(current.first_cluster)?;
+                    }
+                }
+                name => current = self.find_entry(current.first_cluster, name)?,
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Open the file described by `entry` for reading.
+    pub fn open_file(&self, entry: &DirEntry) -> Result<File<'_, B, T>, FatError> {
+        File::new(self, entry)
+    }
+
+    /// Load the file at `path` into `buf` without allocating.
+    ///
+    /// Returns the number of bytes loaded, or [`FatError::BufferTooSmall`]
+    /// before reading anything if the file does not fit.
+    pub fn load_file(&self, path: &str, buf: &mut [u8]) -> Result<usize, FatError> {
+        let entry = self.open_path(path)?;
+        let mut file = File::new(self, &entry)?;
+        let len = file.len() as usize;
+        if len > buf.len() {
+            return Err(FatError::BufferTooSmall);
+        }
+
+        let mut done = 0;
+        while done < len {
+            match file.read(&mut buf[done..len])? {
+                0 => return Err(FatError::ChainTooShort),
+                n => done += n,
+            }
+        }
+        Ok(done)
+    }
+
+    /// Stream the file at `path` one sector at a time.
+    ///
+    /// `sink` receives the LBA of each data sector and its contents,
+    /// truncated to the file size on the last sector. Only a single
+    /// 512-byte scratch buffer is used. Returns the number of bytes loaded.
+    pub fn load_file_with<F>(&self, path: &str, mut sink: F) -> Result<usize, FatError>
+    where
+        F: FnMut(u64, &[u8]) -> Result<(), FatError>,
+    {
+        let entry = self.open_path(path)?;
+        if entry.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+
+        let size = entry.size as usize;
+        let mut sector = [0u8; 512];
+        let mut done = 0;
+
+        for cluster in self.cluster_chain(entry.first_cluster) {
+            let lba = self.cluster_lba(cluster?);
+            for i in 0..self.boot.sectors_per_cluster as u64 {
+                if done == size {
+                    return Ok(done);
+                }
+                self.device.read_sector(lba + i, &mut sector);
+                let n = (size - done).min(512);
+                sink(lba + i, &sector[..n])?;
+                done += n;
+            }
+        }
+
+        if done < size {
+            return Err(FatError::ChainTooShort);
+        }
+        Ok(done)
+    }
+
+    /// Whether `cluster` addresses a data cluster of this volume.
+    pub(crate) fn is_data_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster - 2 < self.cluster_count()
+    }
+
+    /// First sector of a data cluster.
+    pub(crate) fn cluster_lba(&self, cluster: u32) -> u64 {
+        self.first_data_sector() + (cluster - 2) as u64 * self.boot.sectors_per_cluster as u64
+    }
+
+    /// The entry of the directory holding the directory at `dir_cluster`,
+    /// found through the grandparent rather than the `..` dot entry.
+    fn parent_entry(&self, dir_cluster: u32) -> Result<DirEntry, FatError> {
+        let root = self.root_cluster();
+        let parent = match self.find_entry(dir_cluster, "..")?.first_cluster {
+            0 => return Ok(DirEntry::root(root)),
+            cluster if cluster == root => return Ok(DirEntry::root(root)),
+            cluster => cluster,
+        };
+        let grandparent = match self.find_entry(parent, "..")?.first_cluster {
+            0 => root,
+            cluster => cluster,
+        };
+
+        for entry in self.read_dir(grandparent) {
+            let entry = entry?;
+            if entry.is_dir() && entry.first_cluster == parent && entry.name != *DOT_NAME && entry.name != *DOTDOT_NAME {
+                return Ok(entry);
+            }
+        }
+        Err(FatError::NotFound)
+    }
+
+    /// Read sector `index` of the first FAT.
+    pub(crate) fn read_fat_sector(&self, index: u64, buf: &mut [u8]) {
+        #[cfg(feature = "alloc")]
+        if self.fat_cache.read(index, buf) {
+            return;
+        }
+        self.device.read_sector(self.boot.reserved_sectors as u64 + index, buf);
+    }
+
+    /// Write the cached FAT sectors to every FAT copy.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn flush_fat(&mut self) {
+        self.fat_cache.flush(&mut self.device, &self.boot);
+    }
+
+    /// Change sector `index` of the FAT through the cache.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn modify_fat_sector(&mut self, index: u64, update: impl FnOnce(&mut [u8; 512])) {
+        self.fat_cache.modify(&mut self.device, &self.boot, index, update);
+    }
 }
@@ -1,23 +1,78 @@
 use crate::{
+    allocator::{find_free_clusters, find_free_clusters_with, AllocationStrategy, Placement},
     block::BlockDevice,
+    directory::{
+        Attributes, DirEntry, EntryLocation, RawSlots, SlotPos, ATTR_DIRECTORY, ATTR_LONG_NAME,
+        ATTR_VOLUME_ID, DIR_ENTRY_SIZE, DOTDOT_NAME, DOT_NAME, ENTRY_DELETED, ENTRY_END, MAX_DIR_ENTRIES,
+    },
     volume::Fat32Volume,
     error::FatError,
+    fat::FatEntry,
+    file::{Cursor, SeekFrom},
+    lfn::{checksum, encode_slot, slots_needed},
+    name::{generate_short_name, normalize_long_name},
+    time::{DateTime, NoTimeSource, TimeSource},
 };
+use alloc::collections::BTreeSet;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cmp::Ordering;
 
-/// Possible attributes for a FAT32 file or directory.
-pub const ATTR_ARCHIVE: u8 = 0x20;
+pub use crate::directory::ATTR_ARCHIVE;
+
+/// What [`create_file_with`] does when the name is already taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfExists {
+    /// Fail with [`FatError::AlreadyExists`].
+    Fail,
+    /// Keep the existing entry and replace its contents. The old clusters
+    /// are freed once the new data and the entry are written.
+    Replace,
+}
 
 /// Create and write a new file to the FAT32 volume (Windows-compliant)
-pub fn create_file<B: BlockDevice>(
-    volume: &mut Fat32Volume<B>,
+///
+/// Fails with [`FatError::AlreadyExists`] if the long or short name of an
+/// entry in the directory matches `filename`, ignoring case.
+pub fn create_file<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    dir_cluster: u32,
+    filename: &str,
+    data: &[u8],
+) -> Result<(), FatError> {
+    create_file_with(volume, dir_cluster, filename, data, IfExists::Fail)
+}
+
+/// Like [`create_file`], with `if_exists` deciding what happens to an
+/// existing file of the same name.
+pub fn create_file_with<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
     dir_cluster: u32,
     filename: &str,
     data: &[u8],
+    if_exists: IfExists,
 ) -> Result<(), FatError> {
+    let name = normalize_long_name(filename)?;
+
+    // Reserve the directory slots first, so that an invalid name or a full
+    // directory fails before any cluster is allocated
+    let target = match volume.find_entry(dir_cluster, name) {
+        Ok(_) if if_exists == IfExists::Fail => return Err(FatError::AlreadyExists),
+        Ok(old) if old.is_dir() => return Err(FatError::IsADirectory),
+        Ok(old) => {
+            check_writable(volume, &old)?;
+            let pos = old.location.ok_or(FatError::NotFound)?.pos;
+            let chain = volume
+                .cluster_chain(old.first_cluster)
+                .collect::<Result<Vec<_>, _>>()?;
+            Target::Replace(pos, chain)
+        }
+        Err(FatError::NotFound) => Target::New(prepare_directory_entry(volume, dir_cluster, name)?),
+        Err(err) => return Err(err),
+    };
+
     let cluster_size = volume.cluster_size() as usize;
-    let clusters_needed = (data.len() + cluster_size - 1) / cluster_size;
+    let clusters_needed = data.len().div_ceil(cluster_size);
 
     // Allocate free clusters
     let free_clusters = find_free_clusters(volume, clusters_needed)?;
@@ -32,61 +87,703 @@ pub fn create_file<B: BlockDevice>(
 
     // Update FAT entries for all FAT copies
     update_fat_entries(volume, &free_clusters)?;
+    note_allocated(volume, &free_clusters);
+
+    // Add directory entry in the directory cluster, or point the replaced
+    // one at the new data before releasing the old chain
+    let first_cluster = free_clusters.first().copied().unwrap_or(0);
+    match target {
+        Target::New(entry) => {
+            add_directory_entry(volume, &entry, ATTR_ARCHIVE, first_cluster, data.len() as u32)
+        }
+        Target::Replace(pos, old_chain) => {
+            update_entry(volume, pos, first_cluster, data.len() as u32);
+            free_chain(volume, &old_chain)
+        }
+    }
+}
+
+/// Directory entry a new file is written to.
+enum Target {
+    /// Slots reserved for a new entry
+    New(NewEntry),
+    /// Short entry of a file being replaced, with its old chain
+    Replace(SlotPos, Vec<u32>),
+}
+
+/// Create an empty subdirectory called `name` in the directory at
+/// `parent_cluster`, returning the new directory's first cluster.
+///
+/// The new cluster is zeroed and starts with the `.` and `..` entries;
+/// `..` holds 0 when the parent is the root directory, as the
+/// specification requires.
+pub fn create_dir<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    parent_cluster: u32,
+    name: &str,
+) -> Result<u32, FatError> {
+    let root = volume.root_cluster();
+    let parent_cluster = if parent_cluster == 0 { root } else { parent_cluster };
+    let name = normalize_long_name(name)?;
+
+    match volume.find_entry(parent_cluster, name) {
+        Ok(_) => return Err(FatError::AlreadyExists),
+        Err(FatError::NotFound) => {}
+        Err(err) => return Err(err),
+    }
+
+    let entry = prepare_directory_entry(volume, parent_cluster, name)?;
+    let cluster = find_free_clusters(volume, 1)?[0];
+
+    // "." and ".." entries
+    let now = volume.now();
+    let dotdot_cluster = if parent_cluster == root { 0 } else { parent_cluster };
+    let mut dots = [0u8; 2 * DIR_ENTRY_SIZE];
+    dots[..DIR_ENTRY_SIZE].copy_from_slice(&short_entry(DOT_NAME, ATTR_DIRECTORY, cluster, 0, now));
+    dots[DIR_ENTRY_SIZE..].copy_from_slice(&short_entry(DOTDOT_NAME, ATTR_DIRECTORY, dotdot_cluster, 0, now));
+
+    write_cluster(volume, cluster, &dots)?;
+    update_fat_entries(volume, &[cluster])?;
+    note_allocated(volume, &[cluster]);
+    add_directory_entry(volume, &entry, ATTR_DIRECTORY, cluster, 0)?;
+
+    Ok(cluster)
+}
+
+/// Create the directory at `path` along with any missing parents,
+/// returning its first cluster. Existing directories are reused.
+pub fn create_dir_all<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    path: &str,
+) -> Result<u32, FatError> {
+    let root = volume.root_cluster();
+    let mut current = root;
+
+    for component in path.split(['/', '\\']).filter(|c| !c.is_empty()) {
+        current = match component {
+            "." => current,
+            ".." if current == root => root,
+            ".." => match volume.find_entry(current, "..")?.first_cluster {
+                0 => root,
+                parent => parent,
+            },
+            name => match volume.find_entry(current, name) {
+                Ok(entry) if entry.is_dir() => entry.first_cluster,
+                Ok(_) => return Err(FatError::NotADirectory),
+                Err(FatError::NotFound) => create_dir(volume, current, name)?,
+                Err(err) => return Err(err),
+            },
+        };
+    }
+
+    Ok(current)
+}
+
+/// Delete the file at `path` and release its clusters.
+///
+/// The short entry and its LFN slots are marked deleted before the chain
+/// is freed in every FAT copy, so an interruption leaves lost clusters
+/// rather than an entry pointing into free space.
+pub fn remove_file<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, path: &str) -> Result<(), FatError> {
+    let entry = volume.open_path(path)?;
+    if entry.is_dir() {
+        return Err(FatError::IsADirectory);
+    }
+    check_writable(volume, &entry)?;
+    let location = entry.location.ok_or(FatError::NotFound)?;
+
+    let clusters = volume
+        .cluster_chain(entry.first_cluster)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    delete_entry(volume, &location)?;
+    free_chain(volume, &clusters)
+}
+
+/// Delete the empty directory at `path` and release its clusters.
+pub fn remove_dir<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, path: &str) -> Result<(), FatError> {
+    let (entry, location) = removable_dir(volume, path)?;
+
+    for child in volume.read_dir(entry.first_cluster) {
+        if !is_dot_entry(&child?) {
+            return Err(FatError::DirectoryNotEmpty);
+        }
+    }
+
+    let clusters = volume
+        .cluster_chain(entry.first_cluster)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    delete_entry(volume, &location)?;
+    free_chain(volume, &clusters)
+}
+
+/// Delete the directory at `path` together with everything below it.
+///
+/// The whole tree is walked before anything is modified, so a corrupted
+/// chain or a read-only entry fails the call without side effects.
+/// Subdirectory entries
+/// pointing back into the root, at an ancestor of `path` or at a directory
+/// already visited are treated as cycles: they are dropped with the tree
+/// but their clusters are not freed a second time. Clusters of the root
+/// directory are never freed, even when a corrupt chain links into them.
+pub fn remove_dir_all<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, path: &str) -> Result<(), FatError> {
+    let (entry, location) = removable_dir(volume, path)?;
+
+    let root = volume
+        .cluster_chain(volume.root_cluster())
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    let mut visited = ancestors(volume, location.dir_cluster)?;
+    visited.extend(&root);
+    let mut clusters = BTreeSet::new();
+    let mut pending = vec![entry.first_cluster];
+    visited.insert(entry.first_cluster);
+
+    while let Some(dir) = pending.pop() {
+        for child in volume.read_dir(dir) {
+            let child = child?;
+            if is_dot_entry(&child) {
+                continue;
+            }
+            check_writable(volume, &child)?;
+            if child.first_cluster == 0 {
+                continue;
+            }
+            if child.is_dir() {
+                if visited.insert(child.first_cluster) {
+                    pending.push(child.first_cluster);
+                }
+            } else if !visited.contains(&child.first_cluster) {
+                for cluster in volume.cluster_chain(child.first_cluster) {
+                    clusters.insert(cluster?);
+                }
+            }
+        }
+        for cluster in volume.cluster_chain(dir) {
+            clusters.insert(cluster?);
+        }
+    }
+
+    let clusters: Vec<u32> = clusters.difference(&root).copied().collect();
+    delete_entry(volume, &location)?;
+    free_chain(volume, &clusters)
+}
+
+/// Rename or move the entry at `from` to `to`, keeping its data,
+/// attributes and timestamps.
+///
+/// The entry gets new LFN slots and a fresh short alias; a case-only
+/// rename of the same entry is allowed. The new entry is written before
+/// the old one is deleted. A moved directory has its `..` entry pointed
+/// at the new parent, and moving a directory below itself is rejected.
+pub fn rename<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, from: &str, to: &str) -> Result<(), FatError> {
+    let entry = volume.open_path(from)?;
+    let location = entry.location.ok_or(FatError::IsRootDirectory)?;
+    if ends_in_dot_entry(from) {
+        return Err(FatError::InvalidFileName);
+    }
+    check_writable(volume, &entry)?;
+
+    let (parent, name) = parent_and_name(volume, to)?;
+
+    match volume.find_entry(parent, name) {
+        Ok(existing) if existing.location == entry.location => {}
+        Ok(_) => return Err(FatError::AlreadyExists),
+        Err(FatError::NotFound) => {}
+        Err(err) => return Err(err),
+    }
 
-    // Add directory entry in the directory cluster
-    add_directory_entry(volume, dir_cluster, filename, &free_clusters, data.len() as u32)?;
+    let moved_dir = entry.is_dir() && parent != location.dir_cluster;
+    if moved_dir && ancestors(volume, parent)?.contains(&entry.first_cluster) {
+        return Err(FatError::MoveIntoSubdirectory);
+    }
+
+    // everything that can fail is resolved before the first write, so an
+    // error never leaves both entries on disk
+    let old_slots = entry_slots(volume, &location)?;
+    let dotdot = if moved_dir {
+        let dotdot = volume.find_entry(entry.first_cluster, "..")?;
+        Some(dotdot.location.ok_or(FatError::NotFound)?.pos)
+    } else {
+        None
+    };
+
+    let mut short = [0u8; DIR_ENTRY_SIZE];
+    let mut sector = [0u8; 512];
+    volume.device.read_sector(location.pos.lba, &mut sector);
+    short.copy_from_slice(&sector[location.pos.offset..location.pos.offset + DIR_ENTRY_SIZE]);
+
+    let new_entry = prepare_directory_entry(volume, parent, name)?;
+    short[0..11].copy_from_slice(&new_entry.short_name);
+    write_entry(volume, &new_entry, short);
 
+    if let Some(pos) = dotdot {
+        let parent = if parent == volume.root_cluster() { 0 } else { parent };
+        update_slots(volume, &[pos], |_, slot| {
+            slot[20..22].copy_from_slice(&((parent >> 16) as u16).to_le_bytes());
+            slot[26..28].copy_from_slice(&(parent as u16).to_le_bytes());
+        });
+    }
+
+    update_slots(volume, &old_slots, |_, slot| slot[0] = ENTRY_DELETED);
     Ok(())
 }
 
-/// Find free clusters in FAT
-fn find_free_clusters<B: BlockDevice>(
-    volume: &Fat32Volume<B>,
-    count: usize,
-) -> Result<Vec<u32>, FatError> {
-    let mut free_clusters = Vec::new();
-    let fat_start = volume.boot.reserved_sectors as u64;
-    let fat_size = volume.boot.fat_size_sectors as u64;
-    let total_clusters = (fat_size * volume.boot.bytes_per_sector as u64) / 4;
-    let mut fat_sector = [0u8; 512];
-
-    for cluster in 2..total_clusters {
-        let fat_offset = cluster * 4;
-        let sector_index = fat_offset / 512;
-        let byte_index = (fat_offset % 512) as usize;
-
-        if sector_index >= fat_size { break; }
-
-        volume.device.read_sector(fat_start + sector_index, &mut fat_sector);
-        let entry = u32::from_le_bytes([
-            fat_sector[byte_index],
-            fat_sector[byte_index + 1],
-            fat_sector[byte_index + 2],
-            fat_sector[byte_index + 3],
-        ]) & 0x0FFFFFFF;
-
-        if entry == 0 {
-            free_clusters.push(cluster as u32);
-            if free_clusters.len() == count {
-                return Ok(free_clusters);
+/// Replace the attributes of the entry at `path`.
+///
+/// The directory and volume-id bits say what the entry is, so they are
+/// kept as they are whatever `attributes` holds. Read-only entries can be
+/// changed, which is how the flag gets cleared. Paths ending in a `.` or
+/// `..` entry are refused.
+pub fn set_attributes<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    path: &str,
+    attributes: Attributes,
+) -> Result<(), FatError> {
+    let entry = volume.open_path(path)?;
+    let pos = entry.location.ok_or(FatError::IsRootDirectory)?.pos;
+    if ends_in_dot_entry(path) {
+        return Err(FatError::InvalidFileName);
+    }
+
+    let kind = ATTR_DIRECTORY | ATTR_VOLUME_ID;
+    let bits = (attributes.bits() & !kind) | (entry.attributes & kind);
+    update_slots(volume, &[pos], |_, slot| slot[11] = bits);
+    Ok(())
+}
+
+/// Options for opening a file, modelled on `std::fs::OpenOptions`.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    create: bool,
+    create_new: bool,
+    truncate: bool,
+    allocation_strategy: Option<AllocationStrategy>,
+}
+
+impl OpenOptions {
+    /// All options unset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow reading.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Allow writing.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Write at the end of the file whatever the position; implies write.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Create the file if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing with [`FatError::AlreadyExists`] if it exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Truncate an existing file to zero length.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Allocate the file's clusters with `strategy` instead of the
+    /// volume's.
+    pub fn allocation_strategy(&mut self, strategy: AllocationStrategy) -> &mut Self {
+        self.allocation_strategy = Some(strategy);
+        self
+    }
+
+    /// Open the file at `path` with these options.
+    pub fn open<'a, B: BlockDevice, T: TimeSource>(
+        &self,
+        volume: &'a mut Fat32Volume<B, T>,
+        path: &str,
+    ) -> Result<FileHandle<'a, B, T>, FatError> {
+        let writable = self.write || self.append;
+        let creating = self.create || self.create_new;
+        if !(self.read || writable)
+            || (self.truncate && (!self.write || self.append))
+            || (creating && !writable)
+        {
+            return Err(FatError::InvalidOpenOptions);
+        }
+
+        let (parent, name) = parent_and_name(volume, path)?;
+        let entry = match volume.find_entry(parent, name) {
+            Ok(_) if self.create_new => return Err(FatError::AlreadyExists),
+            Ok(entry) => entry,
+            Err(FatError::NotFound) if creating => {
+                create_file(volume, parent, name, &[])?;
+                volume.find_entry(parent, name)?
+            }
+            Err(err) => return Err(err),
+        };
+
+        if entry.is_dir() {
+            return Err(FatError::IsADirectory);
+        }
+        if writable {
+            check_writable(volume, &entry)?;
+        }
+
+        let strategy = self.allocation_strategy.unwrap_or(volume.allocation_strategy);
+        let mut file = FileHandle {
+            volume,
+            location: entry.location.ok_or(FatError::NotFound)?,
+            cursor: Cursor::new(entry.first_cluster, entry.size),
+            readable: self.read,
+            writable,
+            append: self.append,
+            strategy,
+            dirty: false,
+        };
+        if self.truncate && entry.size > 0 {
+            file.shrink(0)?;
+        }
+        Ok(file)
+    }
+}
+
+/// How [`FileHandle::set_len`] fills the bytes added when growing a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    /// Write zeros, as `std::fs::File::set_len` does.
+    Zeros,
+    /// Only allocate the clusters; the new bytes keep whatever the disk
+    /// held before.
+    Undefined,
+}
+
+/// Handle on a file opened through [`OpenOptions`].
+///
+/// Clusters are allocated as data is written. The size, first cluster and
+/// modification time in the directory entry are updated by
+/// [`flush`](Self::flush), [`close`](Self::close) or when the handle is
+/// dropped.
+pub struct FileHandle<'a, B: BlockDevice, T: TimeSource = NoTimeSource> {
+    volume: &'a mut Fat32Volume<B, T>,
+    location: EntryLocation,
+    cursor: Cursor,
+    readable: bool,
+    writable: bool,
+    append: bool,
+    strategy: AllocationStrategy,
+    /// Whether the directory entry is out of date.
+    dirty: bool,
+}
+
+impl<B: BlockDevice, T: TimeSource> FileHandle<'_, B, T> {
+    /// File size in bytes.
+    pub fn len(&self) -> u32 {
+        self.cursor.size
+    }
+
+    /// Whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.cursor.size == 0
+    }
+
+    /// Current position.
+    pub fn position(&self) -> u32 {
+        self.cursor.pos
+    }
+
+    /// Move the position. Writing past the end fills the gap with zeros.
+    pub fn seek(&mut self, from: SeekFrom) -> Result<u32, FatError> {
+        self.cursor.pos = self.cursor.seek_target(from)?;
+        Ok(self.cursor.pos)
+    }
+
+    /// Read up to `buf.len()` bytes from the current position.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, FatError> {
+        if !self.readable {
+            return Err(FatError::NotReadable);
+        }
+        self.cursor.read(self.volume, buf)
+    }
+
+    /// Write all of `buf` at the current position, or at the end of the
+    /// file in append mode, allocating clusters as needed. An empty `buf`
+    /// leaves the file untouched, even past the end.
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, FatError> {
+        if !self.writable {
+            return Err(FatError::NotWritable);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.append {
+            self.cursor.pos = self.cursor.size;
+        }
+        if self.cursor.pos as u64 + buf.len() as u64 > u32::MAX as u64 {
+            return Err(FatError::FileTooLarge);
+        }
+
+        if self.cursor.size < self.cursor.pos {
+            self.zero_fill(self.cursor.pos)?;
+        }
+        self.write_at_cursor(buf)?;
+        Ok(buf.len())
+    }
+
+    /// Shrink or grow the file to `len` bytes, leaving the position alone.
+    ///
+    /// Shrinking frees the clusters past the new end, and truncating to 0
+    /// clears the first cluster. Growing allocates the missing clusters and
+    /// fills them as `fill` says.
+    pub fn set_len(&mut self, len: u32, fill: Fill) -> Result<(), FatError> {
+        if !self.writable {
+            return Err(FatError::NotWritable);
+        }
+        match len.cmp(&self.cursor.size) {
+            Ordering::Less => self.shrink(len),
+            Ordering::Equal => Ok(()),
+            Ordering::Greater if fill == Fill::Zeros => {
+                let pos = self.cursor.pos;
+                self.zero_fill(len)?;
+                self.cursor.pos = pos;
+                Ok(())
+            }
+            Ordering::Greater => {
+                // missing clusters are allocated in one go, so a
+                // contiguous strategy can place them together
+                let cluster_size = self.volume.cluster_size();
+                let needed = len.div_ceil(cluster_size);
+                for index in self.cursor.size.div_ceil(cluster_size)..needed {
+                    match self.cursor.cluster_at(self.volume, index) {
+                        Err(FatError::ChainTooShort) => {
+                            self.append_clusters(index, needed - index)?;
+                            break;
+                        }
+                        found => found?,
+                    };
+                }
+                self.cursor.size = len;
+                self.dirty = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the size, first cluster and modification time to the
+    /// directory entry.
+    pub fn flush(&mut self) -> Result<(), FatError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        update_entry(self.volume, self.location.pos, self.cursor.first_cluster, self.cursor.size);
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Flush and close the file.
+    pub fn close(mut self) -> Result<(), FatError> {
+        self.flush()
+    }
+
+    /// Cut the file to `len` bytes. The directory entry is updated before
+    /// the clusters past the new end are released.
+    fn shrink(&mut self, len: u32) -> Result<(), FatError> {
+        let keep = len.div_ceil(self.volume.cluster_size()) as usize;
+        let chain = self
+            .volume
+            .cluster_chain(self.cursor.first_cluster)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let first_cluster = if keep == 0 { 0 } else { self.cursor.first_cluster };
+        let pos = self.cursor.pos;
+        self.cursor = Cursor::new(first_cluster, len);
+        self.cursor.pos = pos;
+        self.dirty = true;
+        self.flush()?;
+
+        if keep > 0 && keep < chain.len() {
+            self.volume.fat_mut().set(chain[keep - 1], FatEntry::EndOfChain)?;
+        }
+        free_chain(self.volume, chain.get(keep..).unwrap_or(&[]))
+    }
+
+    /// Write zeros from the end of the file up to `target`.
+    fn zero_fill(&mut self, target: u32) -> Result<(), FatError> {
+        self.cursor.pos = self.cursor.size;
+        while self.cursor.pos < target {
+            let n = (target - self.cursor.pos).min(512) as usize;
+            self.write_at_cursor(&[0u8; 512][..n])?;
+        }
+        Ok(())
+    }
+
+    /// Write `buf` at the cursor, extending the chain one cluster at a time.
+    fn write_at_cursor(&mut self, buf: &[u8]) -> Result<(), FatError> {
+        let cluster_size = self.volume.cluster_size();
+        let mut sector = [0u8; 512];
+        let mut done = 0;
+
+        while done < buf.len() {
+            let pos = self.cursor.pos;
+            let index = pos / cluster_size;
+            let cluster = match self.cursor.cluster_at(self.volume, index) {
+                Err(FatError::ChainTooShort) => self.append_clusters(index, 1)?,
+                found => found?,
+            };
+            let in_cluster = pos % cluster_size;
+            let lba = self.volume.cluster_lba(cluster) + (in_cluster / 512) as u64;
+            let in_sector = (in_cluster % 512) as usize;
+            let n = (512 - in_sector).min(buf.len() - done);
+
+            if n == 512 {
+                self.volume.device.write_sector(lba, &buf[done..done + 512]);
+            } else {
+                self.volume.device.read_sector(lba, &mut sector);
+                sector[in_sector..in_sector + n].copy_from_slice(&buf[done..done + n]);
+                self.volume.device.write_sector(lba, &sector);
+            }
+
+            done += n;
+            self.cursor.pos += n as u32;
+            self.cursor.size = self.cursor.size.max(self.cursor.pos);
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Allocate `count` clusters from the `index`-th cluster of the file on,
+    /// linking them after the current last cluster. Returns the first one.
+    fn append_clusters(&mut self, index: u32, count: u32) -> Result<u32, FatError> {
+        let last = match index {
+            0 => None,
+            _ => Some(self.cursor.cluster_at(self.volume, index - 1)?),
+        };
+        let placement = Placement::Append(last);
+        let clusters = find_free_clusters_with(self.volume, count as usize, self.strategy, placement)?;
+        update_fat_entries(self.volume, &clusters)?;
+        match last {
+            Some(last) => {
+                self.volume.fat_mut().set(last, FatEntry::Next(clusters[0]))?;
             }
+            None => self.cursor.first_cluster = clusters[0],
+        }
+        note_allocated(self.volume, &clusters);
+        self.dirty = true;
+        self.cursor.cluster_at(self.volume, index)
+    }
+}
+
+impl<B: BlockDevice, T: TimeSource> Drop for FileHandle<'_, B, T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Split `path` into the first cluster of its parent directory and its
+/// normalized final component.
+fn parent_and_name<'p, B: BlockDevice, T: TimeSource>(
+    volume: &Fat32Volume<B, T>,
+    path: &'p str,
+) -> Result<(u32, &'p str), FatError> {
+    let path = path.trim_end_matches(['/', '\\']);
+    let (parent, name) = match path.rfind(['/', '\\']) {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("", path),
+    };
+    let name = normalize_long_name(name)?;
+
+    let parent = volume.open_path(parent)?;
+    if !parent.is_dir() {
+        return Err(FatError::NotADirectory);
+    }
+    Ok((parent.first_cluster, name))
+}
+
+/// Look up the directory at `path` for removal, refusing the root, entries
+/// whose cluster 0 would resolve to the root, and paths ending in a `.` or
+/// `..` entry.
+fn removable_dir<B: BlockDevice, T: TimeSource>(
+    volume: &Fat32Volume<B, T>,
+    path: &str,
+) -> Result<(DirEntry, EntryLocation), FatError> {
+    let entry = volume.open_path(path)?;
+    if !entry.is_dir() {
+        return Err(FatError::NotADirectory);
+    }
+    let location = match entry.location {
+        Some(location) if entry.first_cluster != 0 && entry.first_cluster != volume.root_cluster() => {
+            location
         }
+        _ => return Err(FatError::IsRootDirectory),
+    };
+    if ends_in_dot_entry(path) {
+        return Err(FatError::InvalidFileName);
+    }
+    check_writable(volume, &entry)?;
+    Ok((entry, location))
+}
+
+/// Clusters of `dir` and of every directory above it, following `..`
+/// entries up to the root.
+fn ancestors<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, dir: u32) -> Result<BTreeSet<u32>, FatError> {
+    let root = volume.root_cluster();
+    let mut found = BTreeSet::from([root]);
+    let mut current = dir;
+
+    while current != 0 && found.insert(current) {
+        current = match volume.find_entry(current, "..") {
+            Ok(parent) => parent.first_cluster,
+            Err(FatError::NotFound) => break,
+            Err(err) => return Err(err),
+        };
     }
+    Ok(found)
+}
+
+/// Fail with [`FatError::ReadOnly`] if `entry` is read-only, unless the
+/// volume was told to ignore the flag.
+fn check_writable<B: BlockDevice, T: TimeSource>(volume: &Fat32Volume<B, T>, entry: &DirEntry) -> Result<(), FatError> {
+    if entry.is_read_only() && !volume.ignore_read_only {
+        return Err(FatError::ReadOnly);
+    }
+    Ok(())
+}
+
+/// Whether `entry` is the `.` or `..` entry of a subdirectory.
+fn is_dot_entry(entry: &DirEntry) -> bool {
+    entry.name == *DOT_NAME || entry.name == *DOTDOT_NAME
+}
 
-    Err(FatError::NoFreeClusters)
+/// Whether the last component of `path` is `.` or `..`, which resolve to
+/// another directory's entry rather than naming one of their own.
+fn ends_in_dot_entry(path: &str) -> bool {
+    matches!(path.rsplit(['/', '\\']).find(|component| !component.is_empty()), Some("." | ".."))
 }
 
 /// Write data to a cluster with zero-padding
-fn write_cluster<B: BlockDevice>(
-    volume: &mut Fat32Volume<B>,
+fn write_cluster<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
     cluster: u32,
     data: &[u8],
 ) -> Result<(), FatError> {
     let cluster_size = volume.cluster_size() as usize;
-    let first_data_sector = volume.boot.reserved_sectors as u64
-        + (volume.boot.fat_count as u64 * volume.boot.fat_size_sectors as u64);
-    let sector_number = first_data_sector
-        + ((cluster - 2) as u64 * volume.boot.sectors_per_cluster as u64);
+    let sector_number = volume.cluster_lba(cluster);
 
     let mut cluster_buf = vec![0u8; cluster_size];
     cluster_buf.fill(0);
@@ -101,154 +798,282 @@ fn write_cluster<B: BlockDevice>(
     Ok(())
 }
 
-/// Update FAT entries and write to all FAT copies
-fn update_fat_entries<B: BlockDevice>(
-    volume: &mut Fat32Volume<B>,
+/// Chain `clusters` together in the FAT, ending with an end-of-chain mark.
+fn update_fat_entries<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
     clusters: &[u32],
 ) -> Result<(), FatError> {
+    let mut fat = volume.fat_mut();
     for (i, &cluster) in clusters.iter().enumerate() {
-        let next_cluster = if i == clusters.len() - 1 {
-            0x0FFFFFFF // End-of-chain
-        } else {
-            clusters[i + 1]
+        let entry = match clusters.get(i + 1) {
+            Some(&next) => FatEntry::Next(next),
+            None => FatEntry::EndOfChain,
         };
+        fat.set(cluster, entry)?;
+    }
+
+    Ok(())
+}
+
+/// Mark every cluster of a chain free and write the change out. Callers
+/// drop the entry pointing at the chain first.
+fn free_chain<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    clusters: &[u32],
+) -> Result<(), FatError> {
+    let mut fat = volume.fat_mut();
+    for &cluster in clusters {
+        fat.set(cluster, FatEntry::Free)?;
+    }
+    volume.flush_fat();
+    Ok(())
+}
+
+/// Record in the next-fit and FSInfo hints that `clusters` were allocated.
+fn note_allocated<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, clusters: &[u32]) {
+    let Some(&last) = clusters.last() else { return };
+    volume.next_free_hint = last.checked_add(1);
+    volume.update_fsinfo(|info| info.next_free = Some(last));
+}
+
+/// Directory slots reserved for a new entry, with the names to store there.
+struct NewEntry {
+    short_name: [u8; 11],
+    long_name: Option<Vec<u16>>,
+    slots: Vec<SlotPos>,
+}
+
+/// Validate `filename`, pick its short alias and reserve enough contiguous
+/// free slots in the directory for the LFN slots and the short entry,
+/// growing the directory if needed.
+fn prepare_directory_entry<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    dir_cluster: u32,
+    filename: &str,
+) -> Result<NewEntry, FatError> {
+    let long_name = normalize_long_name(filename)?;
+    let existing = short_names(volume, dir_cluster)?;
+    let short = generate_short_name(long_name, |candidate| existing.contains(candidate))?;
 
-        let fat_offset = cluster as u64 * 4;
-        let sector_index = fat_offset / 512;
-        let byte_index = (fat_offset % 512) as usize;
+    let long_name: Option<Vec<u16>> = short.needs_lfn.then(|| long_name.encode_utf16().collect());
+    let count = long_name.as_ref().map_or(0, |units| slots_needed(units.len())) + 1;
+    let slots = find_free_slots(volume, dir_cluster, count)?;
 
-        // Update all FATs
-        for fat_copy in 0..volume.boot.fat_count {
-            let fat_base = volume.boot.reserved_sectors as u64
-                + fat_copy as u64 * volume.boot.fat_size_sectors as u64;
+    Ok(NewEntry { short_name: short.name, long_name, slots })
+}
 
-            let mut fat_sector = [0u8; 512];
-            volume.device.read_sector(fat_base + sector_index, &mut fat_sector);
-            fat_sector[byte_index..byte_index + 4].copy_from_slice(&next_cluster.to_le_bytes());
-            volume.device.write_sector(fat_base + sector_index, &fat_sector);
+/// Short names of every live entry in a directory.
+fn short_names<B: BlockDevice, T: TimeSource>(
+    volume: &Fat32Volume<B, T>,
+    dir_cluster: u32,
+) -> Result<Vec<[u8; 11]>, FatError> {
+    let mut names = Vec::new();
+    for slot in RawSlots::new(volume, dir_cluster) {
+        let (_, slot) = slot?;
+        if slot[0] == ENTRY_END {
+            break;
+        }
+        if slot[0] == ENTRY_DELETED || slot[11] & ATTR_LONG_NAME == ATTR_LONG_NAME {
+            continue;
         }
+        let mut name = [0u8; 11];
+        name.copy_from_slice(&slot[..11]);
+        names.push(name);
     }
+    Ok(names)
+}
 
-    Ok(())
+/// Find `count` consecutive free slots (deleted or past the end marker),
+/// growing the directory by zeroed clusters when it has no room left.
+fn find_free_slots<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    dir_cluster: u32,
+    count: usize,
+) -> Result<Vec<SlotPos>, FatError> {
+    let dir_cluster = if dir_cluster == 0 { volume.root_cluster() } else { dir_cluster };
+    let mut run = Vec::with_capacity(count);
+    let mut total_slots = 0;
+
+    for slot in RawSlots::new(volume, dir_cluster) {
+        let (pos, slot) = slot?;
+        total_slots += 1;
+        if slot[0] == ENTRY_END || slot[0] == ENTRY_DELETED {
+            run.push(pos);
+            if run.len() == count {
+                return Ok(run);
+            }
+        } else {
+            run.clear();
+        }
+    }
+
+    // `run` now holds the free slots at the very end of the directory,
+    // which the new clusters extend
+    let slots_per_cluster = volume.cluster_size() as usize / DIR_ENTRY_SIZE;
+    let mut last_cluster = volume.cluster_chain(dir_cluster).try_fold(0, |_, cluster| cluster)?;
+
+    while run.len() < count {
+        if total_slots + slots_per_cluster > MAX_DIR_ENTRIES {
+            return Err(FatError::NoFreeDirectoryEntry);
+        }
+
+        let cluster = find_free_clusters(volume, 1)?[0];
+        write_cluster(volume, cluster, &[])?;
+        update_fat_entries(volume, &[last_cluster, cluster])?;
+        note_allocated(volume, &[cluster]);
+
+        let lba = volume.cluster_lba(cluster);
+        for i in 0..slots_per_cluster {
+            let offset = i * DIR_ENTRY_SIZE;
+            run.push(SlotPos { lba: lba + (offset / 512) as u64, offset: offset % 512 });
+        }
+        total_slots += slots_per_cluster;
+        last_cluster = cluster;
+    }
+
+    run.truncate(count);
+    Ok(run)
 }
 
 /// Add directory entry for the new file (Windows-compatible)
-fn add_directory_entry<B: BlockDevice>(
-    volume: &mut Fat32Volume<B>,
-    dir_cluster: u32,
-    filename: &str,
-    clusters: &[u32],
+fn add_directory_entry<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    entry: &NewEntry,
+    attributes: u8,
+    first_cluster: u32,
     file_size: u32,
 ) -> Result<(), FatError> {
-    let cluster_size = volume.cluster_size() as usize;
-    let first_data_sector = volume.boot.reserved_sectors as u64
-        + (volume.boot.fat_count as u64 * volume.boot.fat_size_sectors as u64);
-    let mut current_cluster = dir_cluster;
-
-    loop {
-        let sector_number = first_data_sector
-            + ((current_cluster - 2) as u64 * volume.boot.sectors_per_cluster as u64);
-        let mut sector_buf = vec![0u8; cluster_size];
-
-        // read all sectors in cluster
-        for i in 0..volume.boot.sectors_per_cluster {
-            volume
-                .device
-                .read_sector(sector_number + i as u64, &mut sector_buf[(i as usize) * 512..(i as usize + 1) * 512]);
-        }
-
-        // iterate directory entries (32 bytes each)
-        for entry_index in 0..(cluster_size / 32) {
-            let entry_offset = entry_index * 32;
-            if sector_buf[entry_offset] == 0x00 || sector_buf[entry_offset] == 0xE5 {
-                let mut entry = [0u8; 32];
-
-                // filename 8.3 format (uppercase)
-                let (name, ext) = if let Some(dot_pos) = filename.find('.') {
-                    (&filename[..dot_pos], &filename[dot_pos + 1..])
-                } else {
-                    (filename, "")
-                };
-                let name_bytes = name.as_bytes();
-                let ext_bytes = ext.as_bytes();
-
-                for i in 0..8 {
-                    entry[i] = if i < name_bytes.len() { name_bytes[i].to_ascii_uppercase() } else { b' ' };
-                }
-                for i in 0..3 {
-                    entry[8 + i] = if i < ext_bytes.len() { ext_bytes[i].to_ascii_uppercase() } else { b' ' };
-                }
+    let short = short_entry(&entry.short_name, attributes, first_cluster, file_size, volume.now());
+    write_entry(volume, entry, short);
+    Ok(())
+}
 
-                // file attribute
-                entry[11] = ATTR_ARCHIVE;
-
-                // reserved for Windows: set 0
-                entry[12] = 0; // reserved NT
-                entry[13] = 0; // creation time tenths
-
-                // timestamps fake (to be Windows-compatible)
-                entry[14..16].copy_from_slice(&0x0000u16.to_le_bytes()); // creation time
-                entry[16..18].copy_from_slice(&0x0000u16.to_le_bytes()); // creation date
-                entry[18..20].copy_from_slice(&0x0000u16.to_le_bytes()); // last access date
-                entry[22..24].copy_from_slice(&0x0000u16.to_le_bytes()); // last write time
-                entry[24..26].copy_from_slice(&0x0000u16.to_le_bytes()); // last write date
-
-                // first cluster
-                let first_cluster = clusters[0];
-                entry[20..22].copy_from_slice(&(first_cluster as u16).to_le_bytes()); // low
-                entry[26..28].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes()); // high
-
-                // file size
-                entry[28..32].copy_from_slice(&file_size.to_le_bytes());
-
-                // write entry back to sector buffer
-                sector_buf[entry_offset..entry_offset + 32].copy_from_slice(&entry);
-
-                // write all sectors back
-                for i in 0..volume.boot.sectors_per_cluster {
-                    volume.device.write_sector(
-                        sector_number + i as u64,
-                        &sector_buf[(i as usize) * 512..(i as usize + 1) * 512],
-                    );
-                }
+/// Write the LFN slots of `entry` followed by the short entry `short`,
+/// whose name field must already hold `entry.short_name`.
+fn write_entry<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, entry: &NewEntry, short: [u8; DIR_ENTRY_SIZE]) {
+    let mut raw = Vec::with_capacity(entry.slots.len());
 
-                return Ok(());
-            }
+    // LFN slots come first, highest sequence number first
+    if let Some(units) = &entry.long_name {
+        let checksum = checksum(&entry.short_name);
+        for seq in (1..=slots_needed(units.len())).rev() {
+            raw.push(encode_slot(units, seq as u8, checksum));
         }
+    }
+
+    raw.push(short);
+    write_slots(volume, &entry.slots, &raw);
+}
+
+/// Build a short directory entry.
+fn short_entry(
+    name: &[u8; 11],
+    attributes: u8,
+    first_cluster: u32,
+    file_size: u32,
+    now: DateTime,
+) -> [u8; DIR_ENTRY_SIZE] {
+    let mut entry = [0u8; DIR_ENTRY_SIZE];
+
+    // filename 8.3 format (uppercase)
+    entry[0..11].copy_from_slice(name);
+
+    // file attribute
+    entry[11] = attributes;
+
+    // reserved for Windows: set 0
+    entry[12] = 0; // reserved NT
+
+    // timestamps
+    entry[13] = now.fat_tenths(); // creation time tenths
+    entry[14..16].copy_from_slice(&now.fat_time().to_le_bytes()); // creation time
+    entry[16..18].copy_from_slice(&now.fat_date().to_le_bytes()); // creation date
+    entry[18..20].copy_from_slice(&now.fat_date().to_le_bytes()); // last access date
+    entry[22..24].copy_from_slice(&now.fat_time().to_le_bytes()); // last write time
+    entry[24..26].copy_from_slice(&now.fat_date().to_le_bytes()); // last write date
 
-        // move to next cluster in directory
-        let next_cluster = get_next_cluster(volume, current_cluster)?;
-        if next_cluster >= 0x0FFFFFF8 {
-            break; // end of directory
+    // first cluster
+    entry[20..22].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes()); // high
+    entry[26..28].copy_from_slice(&(first_cluster as u16).to_le_bytes()); // low
+
+    // file size
+    entry[28..32].copy_from_slice(&file_size.to_le_bytes());
+
+    entry
+}
+
+/// Write `raw` slots at `positions`.
+fn write_slots<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    positions: &[SlotPos],
+    raw: &[[u8; DIR_ENTRY_SIZE]],
+) {
+    update_slots(volume, positions, |i, slot| slot.copy_from_slice(&raw[i]));
+}
+
+/// Apply `update` to the slots at `positions`, rewriting each touched
+/// sector once.
+///
+/// Cached FAT changes are written out first, so an entry never reaches
+/// the device before the chain it points to.
+fn update_slots<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    positions: &[SlotPos],
+    mut update: impl FnMut(usize, &mut [u8]),
+) {
+    volume.flush_fat();
+    let mut sector = [0u8; 512];
+    let mut current = None;
+
+    for (i, pos) in positions.iter().enumerate() {
+        if current != Some(pos.lba) {
+            if let Some(lba) = current {
+                volume.device.write_sector(lba, &sector);
+            }
+            volume.device.read_sector(pos.lba, &mut sector);
+            current = Some(pos.lba);
         }
-        current_cluster = next_cluster;
+        update(i, &mut sector[pos.offset..pos.offset + DIR_ENTRY_SIZE]);
     }
 
-    Err(FatError::NoFreeDirectoryEntry)
+    if let Some(lba) = current {
+        volume.device.write_sector(lba, &sector);
+    }
 }
 
+/// Point the short entry at `pos` to new contents, stamping it modified.
+fn update_entry<B: BlockDevice, T: TimeSource>(volume: &mut Fat32Volume<B, T>, pos: SlotPos, first_cluster: u32, size: u32) {
+    let now = volume.now();
+    update_slots(volume, &[pos], |_, slot| {
+        slot[11] |= ATTR_ARCHIVE;
+        slot[18..20].copy_from_slice(&now.fat_date().to_le_bytes());
+        slot[20..22].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes());
+        slot[22..24].copy_from_slice(&now.fat_time().to_le_bytes());
+        slot[24..26].copy_from_slice(&now.fat_date().to_le_bytes());
+        slot[26..28].copy_from_slice(&(first_cluster as u16).to_le_bytes());
+        slot[28..32].copy_from_slice(&size.to_le_bytes());
+    });
+}
 
-/// Read next cluster in FAT chain
-fn get_next_cluster<B: BlockDevice>(
-    volume: &Fat32Volume<B>,
-    cluster: u32,
-) -> Result<u32, FatError> {
-    let fat_start = volume.boot.reserved_sectors as u64;
-    let fat_size = volume.boot.fat_size_sectors as u64;
-    let fat_offset = cluster as u64 * 4;
-    let sector_index = fat_offset / 512;
-    let byte_index = (fat_offset % 512) as usize;
-
-    if sector_index >= fat_size { return Err(FatError::InvalidCluster); }
-
-    let mut fat_sector = [0u8; 512];
-    volume.device.read_sector(fat_start + sector_index, &mut fat_sector);
-    let entry = u32::from_le_bytes([
-        fat_sector[byte_index],
-        fat_sector[byte_index+1],
-        fat_sector[byte_index+2],
-        fat_sector[byte_index+3],
-    ]) & 0x0FFFFFFF;
-
-    Ok(entry)
+/// Mark the short entry at `location` and its LFN slots deleted.
+fn delete_entry<B: BlockDevice, T: TimeSource>(
+    volume: &mut Fat32Volume<B, T>,
+    location: &EntryLocation,
+) -> Result<(), FatError> {
+    let positions = entry_slots(volume, location)?;
+    update_slots(volume, &positions, |_, slot| slot[0] = ENTRY_DELETED);
+    Ok(())
+}
+
+/// Positions of the LFN slots and the short entry at `location`.
+fn entry_slots<B: BlockDevice, T: TimeSource>(
+    volume: &Fat32Volume<B, T>,
+    location: &EntryLocation,
+) -> Result<Vec<SlotPos>, FatError> {
+    let first = (location.index - location.lfn_slots as u32) as usize;
+    RawSlots::new(volume, location.dir_cluster)
+        .skip(first)
+        .take(location.lfn_slots as usize + 1)
+        .map(|slot| slot.map(|(pos, _)| pos))
+        .collect()
 }
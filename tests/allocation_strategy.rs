@@ -0,0 +1,122 @@
+mod common;
+
+use common::{set_fat_entry, small_volume, CountingDevice, MemBlockDevice};
+use no_std::allocator::AllocationStrategy;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, remove_file, Fill, OpenOptions};
+
+/// A volume whose clusters 3..100 are in use except for free runs of
+/// 2, 4 and 6 clusters at 10, 20 and 40.
+fn fragmented_volume() -> Fat32Volume<MemBlockDevice> {
+    let (mut dev, layout) = small_volume();
+    for cluster in 3..100 {
+        let hole = (10..12).contains(&cluster) || (20..24).contains(&cluster) || (40..46).contains(&cluster);
+        if !hole {
+            set_fat_entry(&mut dev, &layout, cluster, 0x0FFFFFFF);
+        }
+    }
+    Fat32Volume::open(dev).unwrap()
+}
+
+fn chain(vol: &Fat32Volume<MemBlockDevice>, path: &str) -> Vec<u32> {
+    let first = vol.open_path(path).unwrap().first_cluster;
+    vol.cluster_chain(first).map(Result::unwrap).collect()
+}
+
+#[test]
+fn first_fit_reuses_lowest_free_cluster() {
+    let mut vol = fragmented_volume();
+    vol.set_allocation_strategy(AllocationStrategy::FirstFit);
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    remove_file(&mut vol, "/a.bin").unwrap();
+    create_file(&mut vol, 2, "b.bin", b"b").unwrap();
+    assert_eq!(chain(&vol, "/b.bin"), [10]);
+}
+
+#[test]
+fn next_fit_resumes_after_last_allocation() {
+    let mut vol = fragmented_volume();
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    assert_eq!(chain(&vol, "/a.bin"), [10]);
+    remove_file(&mut vol, "/a.bin").unwrap();
+    create_file(&mut vol, 2, "b.bin", &[1u8; 1024]).unwrap();
+    assert_eq!(chain(&vol, "/b.bin"), [11, 20]);
+}
+
+#[test]
+fn best_fit_picks_smallest_sufficient_run() {
+    let mut vol = fragmented_volume();
+    vol.set_allocation_strategy(AllocationStrategy::BestFitContiguous);
+    create_file(&mut vol, 2, "five.bin", &[5u8; 2500]).unwrap();
+    create_file(&mut vol, 2, "three.bin", &[3u8; 1500]).unwrap();
+    create_file(&mut vol, 2, "seven.bin", &[7u8; 3500]).unwrap();
+    assert_eq!(chain(&vol, "/five.bin"), [40, 41, 42, 43, 44]);
+    assert_eq!(chain(&vol, "/three.bin"), [20, 21, 22]);
+    assert_eq!(chain(&vol, "/seven.bin"), (100..107).collect::<Vec<_>>());
+}
+
+#[test]
+fn growing_file_continues_after_its_last_cluster() {
+    let mut vol = fragmented_volume();
+    vol.set_allocation_strategy(AllocationStrategy::BestFitContiguous);
+    create_file(&mut vol, 2, "log.txt", &[1u8; 512]).unwrap();
+    assert_eq!(chain(&vol, "/log.txt"), [10]);
+
+    let mut file = OpenOptions::new().append(true).open(&mut vol, "/log.txt").unwrap();
+    file.write(&[2u8; 512]).unwrap();
+    file.close().unwrap();
+    assert_eq!(chain(&vol, "/log.txt"), [10, 11]);
+}
+
+#[test]
+fn open_options_override_volume_strategy() {
+    let mut vol = fragmented_volume();
+    vol.set_allocation_strategy(AllocationStrategy::FirstFit);
+    create_file(&mut vol, 2, "a.bin", &[]).unwrap();
+    create_file(&mut vol, 2, "b.bin", &[]).unwrap();
+
+    let mut file = OpenOptions::new().write(true).open(&mut vol, "/a.bin").unwrap();
+    file.set_len(1500, Fill::Undefined).unwrap();
+    file.close().unwrap();
+    assert_eq!(chain(&vol, "/a.bin"), [10, 11, 20]);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .allocation_strategy(AllocationStrategy::BestFitContiguous)
+        .open(&mut vol, "/b.bin")
+        .unwrap();
+    file.set_len(1500, Fill::Undefined).unwrap();
+    file.close().unwrap();
+    // a handle may keep growing, so it starts in the largest free run
+    assert_eq!(chain(&vol, "/b.bin"), [100, 101, 102]);
+}
+
+#[test]
+fn best_fit_streaming_write_stays_contiguous() {
+    let (mut dev, layout) = small_volume();
+    // single free clusters at 10, 20, ..., 290, then free space from 300 on
+    for cluster in (3..300).filter(|cluster| cluster % 10 != 0) {
+        set_fat_entry(&mut dev, &layout, cluster, 0x0FFFFFFF);
+    }
+    let dev = CountingDevice::new(dev, layout.first_fat());
+    let reads = dev.reads.clone();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "log.bin", &[]).unwrap();
+
+    reads.set(0);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .allocation_strategy(AllocationStrategy::BestFitContiguous)
+        .open(&mut vol, "/log.bin")
+        .unwrap();
+    for _ in 0..20 {
+        file.write(&[7u8; 512]).unwrap();
+    }
+    file.close().unwrap();
+
+    let first = vol.open_path("/log.bin").unwrap().first_cluster;
+    let chain: Vec<u32> = vol.cluster_chain(first).map(Result::unwrap).collect();
+    assert_eq!(chain, (300..320).collect::<Vec<_>>());
+    // one pass over the FAT to find the largest run, not one per cluster
+    assert!(reads.get() <= layout.fat_size_sectors as usize + 10, "{} FAT reads", reads.get());
+}
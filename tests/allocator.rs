@@ -0,0 +1,125 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use common::{set_fat_entry, small_volume, CountingDevice, Layout, MemBlockDevice};
+use no_std::allocator::AllocationStrategy;
+use no_std::block::BlockDevice;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, remove_file};
+
+fn set_next_free(dev: &mut MemBlockDevice, next_free: u32) {
+    let mut sector = dev.read(1);
+    sector[492..496].copy_from_slice(&next_free.to_le_bytes());
+    dev.write(1, &sector);
+}
+
+/// Open `dev`, counting reads of the first FAT.
+fn counting(dev: MemBlockDevice, layout: &Layout) -> (Fat32Volume<CountingDevice>, Rc<Cell<usize>>) {
+    let dev = CountingDevice::new(dev, layout.first_fat());
+    let reads = dev.reads.clone();
+    (Fat32Volume::open(dev).unwrap(), reads)
+}
+
+fn first_cluster<B: BlockDevice>(vol: &Fat32Volume<B>, path: &str) -> u32 {
+    vol.open_path(path).unwrap().first_cluster
+}
+
+#[test]
+fn starts_at_next_free_hint() {
+    let (mut dev, layout) = small_volume();
+    set_next_free(&mut dev, 60_000);
+    for cluster in 60_000..60_400 {
+        set_fat_entry(&mut dev, &layout, cluster, 0x0FFFFFFF);
+    }
+    let (mut vol, reads) = counting(dev, &layout);
+
+    reads.set(0);
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    assert_eq!(first_cluster(&vol, "/a.bin"), 60_400);
+    // four FAT sectors scanned instead of one read per used cluster; the
+    // rest follow the root directory's chain and update the entry
+    assert!(reads.get() <= 12, "{} FAT reads", reads.get());
+    assert_eq!(vol.fsinfo().unwrap().next_free, Some(60_400));
+
+    create_file(&mut vol, 2, "b.bin", &[0u8; 1024]).unwrap();
+    let chain: Vec<u32> = vol.cluster_chain(first_cluster(&vol, "/b.bin")).map(Result::unwrap).collect();
+    assert_eq!(chain, [60_401, 60_402]);
+}
+
+#[test]
+fn wraps_around_to_start_of_fat() {
+    let (mut dev, layout) = small_volume();
+    let last = layout.cluster_count() + 1;
+    set_next_free(&mut dev, last);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "a.bin", &[0u8; 1024]).unwrap();
+    let chain: Vec<u32> = vol.cluster_chain(first_cluster(&vol, "/a.bin")).map(Result::unwrap).collect();
+    assert_eq!(chain, [last, 3]);
+}
+
+#[test]
+fn ignores_fat_entries_past_the_last_cluster() {
+    let (mut dev, layout) = small_volume();
+    for cluster in 3..layout.cluster_count() + 2 {
+        set_fat_entry(&mut dev, &layout, cluster, 0x0FFFFFF7);
+    }
+    // the FAT has room for more entries than the volume has clusters
+    assert!(layout.fat_size_sectors * 128 > layout.cluster_count() + 2);
+
+    let mut vol = Fat32Volume::open(dev.clone()).unwrap();
+    assert_eq!(create_file(&mut vol, 2, "a.bin", b"a").unwrap_err(), FatError::NoFreeClusters);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    vol.set_free_bitmap_limit(1 << 20);
+    assert_eq!(create_file(&mut vol, 2, "a.bin", b"a").unwrap_err(), FatError::NoFreeClusters);
+}
+
+#[test]
+fn bitmap_avoids_fat_scans() {
+    let (mut dev, layout) = small_volume();
+    for cluster in 3..60_000 {
+        set_fat_entry(&mut dev, &layout, cluster, 0x0FFFFFFF);
+    }
+    // first-fit starts every allocation at cluster 2
+    let (mut vol, reads) = counting(dev.clone(), &layout);
+    vol.set_allocation_strategy(AllocationStrategy::FirstFit);
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    reads.set(0);
+    create_file(&mut vol, 2, "b.bin", b"b").unwrap();
+    assert!(reads.get() > 400, "{} FAT reads", reads.get());
+    let scanned = (first_cluster(&vol, "/a.bin"), first_cluster(&vol, "/b.bin"));
+
+    let (mut vol, reads) = counting(dev, &layout);
+    vol.set_allocation_strategy(AllocationStrategy::FirstFit);
+    vol.set_free_bitmap_limit(16 * 1024);
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    reads.set(0);
+    create_file(&mut vol, 2, "b.bin", b"b").unwrap();
+    assert!(reads.get() <= 8, "{} FAT reads", reads.get());
+    assert_eq!((first_cluster(&vol, "/a.bin"), first_cluster(&vol, "/b.bin")), scanned);
+
+    // freed clusters become available again
+    remove_file(&mut vol, "/a.bin").unwrap();
+    create_file(&mut vol, 2, "c.bin", b"c").unwrap();
+    assert_eq!(first_cluster(&vol, "/c.bin"), scanned.0);
+}
+
+#[test]
+fn bitmap_is_size_bounded() {
+    let (dev, layout) = small_volume();
+    let (mut vol, reads) = counting(dev, &layout);
+
+    // one bit per cluster does not fit in 1 KiB
+    vol.set_free_bitmap_limit(1024);
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    reads.set(0);
+    create_file(&mut vol, 2, "b.bin", b"b").unwrap();
+    assert!(reads.get() < 12, "{} FAT reads", reads.get());
+    vol.set_free_bitmap_limit(16 * 1024);
+    create_file(&mut vol, 2, "c.bin", b"c").unwrap();
+    assert!(reads.get() > 500, "{} FAT reads", reads.get());
+}
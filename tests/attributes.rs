@@ -0,0 +1,106 @@
+mod common;
+
+use common::small_volume;
+use no_std::directory::Attributes;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{
+    create_dir, create_file, create_file_with, remove_dir, remove_dir_all, remove_file, rename,
+    set_attributes, IfExists, OpenOptions,
+};
+
+#[test]
+fn attribute_set_operations() {
+    let mut attrs = Attributes::READ_ONLY | Attributes::HIDDEN;
+    assert!(attrs.contains(Attributes::READ_ONLY));
+    assert!(!attrs.contains(Attributes::READ_ONLY | Attributes::SYSTEM));
+    attrs.insert(Attributes::ARCHIVE);
+    attrs.remove(Attributes::READ_ONLY);
+    assert_eq!(attrs.bits(), 0x22);
+    assert_eq!(Attributes::from_bits(0xFF).bits(), 0x3F);
+    assert_eq!(Attributes::default(), Attributes::empty());
+}
+
+#[test]
+fn sets_and_reads_attributes() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "boot.cfg", b"cfg").unwrap();
+    assert_eq!(vol.open_path("/boot.cfg").unwrap().attributes(), Attributes::ARCHIVE);
+
+    set_attributes(&mut vol, "/boot.cfg", Attributes::HIDDEN | Attributes::SYSTEM).unwrap();
+    let entry = vol.open_path("/boot.cfg").unwrap();
+    assert_eq!(entry.attributes(), Attributes::HIDDEN | Attributes::SYSTEM);
+    assert!(!entry.is_read_only());
+
+    // the directory bit cannot be set or cleared
+    create_dir(&mut vol, 2, "dir").unwrap();
+    set_attributes(&mut vol, "/dir", Attributes::HIDDEN).unwrap();
+    assert_eq!(vol.open_path("/dir").unwrap().attributes(), Attributes::HIDDEN | Attributes::DIRECTORY);
+    set_attributes(&mut vol, "/boot.cfg", Attributes::DIRECTORY | Attributes::VOLUME_ID).unwrap();
+    assert_eq!(vol.open_path("/boot.cfg").unwrap().attributes(), Attributes::empty());
+
+    assert_eq!(set_attributes(&mut vol, "/", Attributes::HIDDEN).unwrap_err(), FatError::IsRootDirectory);
+}
+
+#[test]
+fn read_only_entries_are_protected() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "locked.txt", b"keep").unwrap();
+    let dir = create_dir(&mut vol, 2, "dir").unwrap();
+    create_file(&mut vol, dir, "inner.txt", b"x").unwrap();
+    set_attributes(&mut vol, "/locked.txt", Attributes::READ_ONLY).unwrap();
+    set_attributes(&mut vol, "/dir/inner.txt", Attributes::READ_ONLY).unwrap();
+
+    let read_only = FatError::ReadOnly;
+    assert_eq!(remove_file(&mut vol, "/locked.txt").unwrap_err(), read_only);
+    assert_eq!(rename(&mut vol, "/locked.txt", "/other.txt").unwrap_err(), read_only);
+    assert_eq!(create_file_with(&mut vol, 2, "locked.txt", b"new", IfExists::Replace).unwrap_err(), read_only);
+    assert_eq!(OpenOptions::new().write(true).open(&mut vol, "/locked.txt").err(), Some(read_only));
+    assert_eq!(remove_dir_all(&mut vol, "/dir").unwrap_err(), read_only);
+    assert!(OpenOptions::new().read(true).open(&mut vol, "/locked.txt").is_ok());
+    assert_eq!(vol.open_path("/dir/inner.txt").unwrap().size, 1);
+
+    set_attributes(&mut vol, "/dir", Attributes::READ_ONLY).unwrap();
+    vol.set_ignore_read_only(true);
+    remove_file(&mut vol, "/dir/inner.txt").unwrap();
+    vol.set_ignore_read_only(false);
+    assert_eq!(remove_dir(&mut vol, "/dir").unwrap_err(), read_only);
+
+    // clearing the flag lifts the protection
+    set_attributes(&mut vol, "/locked.txt", Attributes::ARCHIVE).unwrap();
+    remove_file(&mut vol, "/locked.txt").unwrap();
+}
+
+#[test]
+fn forced_operations_ignore_read_only() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "a.txt", b"old").unwrap();
+    set_attributes(&mut vol, "/a.txt", Attributes::READ_ONLY).unwrap();
+    vol.set_ignore_read_only(true);
+
+    create_file_with(&mut vol, 2, "a.txt", b"new", IfExists::Replace).unwrap();
+    rename(&mut vol, "/a.txt", "/b.txt").unwrap();
+    let entry = vol.open_path("/b.txt").unwrap();
+    assert!(entry.is_read_only());
+    assert_eq!(entry.size, 3);
+    remove_file(&mut vol, "/b.txt").unwrap();
+}
+
+#[test]
+fn refuses_dot_entries() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let a = create_dir(&mut vol, 2, "a").unwrap();
+    let b = create_dir(&mut vol, a, "b").unwrap();
+
+    let flags = Attributes::HIDDEN | Attributes::READ_ONLY;
+    assert_eq!(set_attributes(&mut vol, "/a/b/..", flags).unwrap_err(), FatError::InvalidFileName);
+    assert_eq!(vol.open_path("/a").unwrap().attributes(), Attributes::DIRECTORY);
+    assert_eq!(vol.find_entry(b, "..").unwrap().attributes(), Attributes::DIRECTORY);
+}
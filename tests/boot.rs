@@ -1,5 +1,9 @@
+mod common;
+
 use no_std::volume::Fat32Volume;
 use no_std::block::BlockDevice;
+use no_std::boot_sector::{BootSector, FatType};
+use no_std::error::FatError;
 
 struct MemBlockDevice {
     data: [u8; 512],
@@ -19,8 +23,12 @@ fn parse_boot_sector() {
     img[13] = 8;
     img[14..16].copy_from_slice(&32u16.to_le_bytes());
     img[16] = 2;
+    img[21] = 0xF8;
+    img[32..36].copy_from_slice(&600_000u32.to_le_bytes());
     img[36..40].copy_from_slice(&1000u32.to_le_bytes());
     img[44..48].copy_from_slice(&2u32.to_le_bytes());
+    img[510] = 0x55;
+    img[511] = 0xAA;
 
     let dev = MemBlockDevice { data: img };
     let vol = Fat32Volume::open(dev).unwrap();
@@ -29,3 +37,52 @@ fn parse_boot_sector() {
     assert_eq!(vol.fat_count(), 2);
     assert_eq!(vol.root_cluster(), 2);
 }
+
+type Corruption = fn(&mut [u8; 512]);
+
+fn valid_sector() -> [u8; 512] {
+    let (dev, _) = common::small_volume();
+    dev.read(0)
+}
+
+#[test]
+fn rejects_invalid_bpb_fields() {
+    assert_eq!(BootSector::parse(&valid_sector()).unwrap().fat_type(), FatType::Fat32);
+
+    let cases: [(Corruption, FatError); 11] = [
+        (|s| s[511] = 0, FatError::MissingBootSignature),
+        (|s| s[11..13].copy_from_slice(&4096u16.to_le_bytes()), FatError::UnsupportedSectorSize),
+        (|s| s[13] = 0, FatError::InvalidSectorsPerCluster),
+        (|s| s[13] = 3, FatError::InvalidSectorsPerCluster),
+        (|s| s[14..16].fill(0), FatError::InvalidReservedSectors),
+        (|s| s[16] = 0, FatError::InvalidFatCount),
+        (|s| s[21] = 0x12, FatError::InvalidMediaDescriptor),
+        (|s| s[36..40].fill(0), FatError::InvalidFatSize),
+        (|s| s[32..36].copy_from_slice(&100u32.to_le_bytes()), FatError::InvalidTotalSectors),
+        (|s| s[44..48].copy_from_slice(&1u32.to_le_bytes()), FatError::InvalidRootCluster),
+        (|s| s[42] = 1, FatError::UnsupportedFatVersion),
+    ];
+
+    for (i, (corrupt, expected)) in cases.iter().enumerate() {
+        let mut sector = valid_sector();
+        corrupt(&mut sector);
+        assert_eq!(BootSector::parse(&sector).unwrap_err(), *expected, "case {i}");
+    }
+}
+
+#[test]
+fn detects_fat_type_from_cluster_count() {
+    assert_eq!(FatType::from_cluster_count(4084), FatType::Fat12);
+    assert_eq!(FatType::from_cluster_count(4085), FatType::Fat16);
+    assert_eq!(FatType::from_cluster_count(65524), FatType::Fat16);
+    assert_eq!(FatType::from_cluster_count(65525), FatType::Fat32);
+
+    // a FAT16-sized volume still claiming "FAT32" in its type string
+    let (dev, _) = common::format(40_000, 1);
+    assert_eq!(BootSector::parse(&dev.read(0)).unwrap_err(), FatError::NotFat32(FatType::Fat16));
+
+    // FAT size too small to describe every cluster
+    let mut sector = valid_sector();
+    sector[36..40].copy_from_slice(&100u32.to_le_bytes());
+    assert_eq!(BootSector::parse(&sector).unwrap_err(), FatError::InvalidFatSize);
+}
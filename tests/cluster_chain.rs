@@ -0,0 +1,60 @@
+mod common;
+
+use common::{set_fat_entry, small_volume};
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+
+#[test]
+fn follows_chain_across_fat_sectors() {
+    let (mut dev, layout) = small_volume();
+    // 10 -> 11 -> 300 -> EOC, where 300 lives in another FAT sector
+    set_fat_entry(&mut dev, &layout, 10, 11);
+    set_fat_entry(&mut dev, &layout, 11, 300);
+    set_fat_entry(&mut dev, &layout, 300, 0x0FFFFFFF);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    let chain: Result<Vec<u32>, FatError> = vol.cluster_chain(10).collect();
+    assert_eq!(chain, Ok(vec![10, 11, 300]));
+    assert_eq!(vol.cluster_chain(0).count(), 0);
+}
+
+#[test]
+fn reports_corrupt_links() {
+    let (mut dev, layout) = small_volume();
+    set_fat_entry(&mut dev, &layout, 10, 11);
+    set_fat_entry(&mut dev, &layout, 11, 0x0FFFFFF7);
+    set_fat_entry(&mut dev, &layout, 20, 0);
+    set_fat_entry(&mut dev, &layout, 30, layout.cluster_count() + 2);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    let items: Vec<_> = vol.cluster_chain(10).collect();
+    assert_eq!(items, vec![Ok(10), Ok(11), Err(FatError::BadCluster)]);
+
+    let items: Vec<_> = vol.cluster_chain(20).collect();
+    assert_eq!(items, vec![Ok(20), Err(FatError::FreeClusterInChain)]);
+
+    let items: Vec<_> = vol.cluster_chain(30).collect();
+    assert_eq!(items, vec![Ok(30), Err(FatError::InvalidCluster)]);
+
+    let items: Vec<_> = vol.cluster_chain(1).collect();
+    assert_eq!(items, vec![Err(FatError::InvalidCluster)]);
+}
+
+#[test]
+fn detects_loops() {
+    let (mut dev, layout) = small_volume();
+    // 10 -> 11 -> 12 -> ... -> 50 -> 30 (cycle of length 21)
+    for cluster in 10..50 {
+        set_fat_entry(&mut dev, &layout, cluster, cluster + 1);
+    }
+    set_fat_entry(&mut dev, &layout, 50, 30);
+    set_fat_entry(&mut dev, &layout, 60, 60);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    let last = vol.cluster_chain(10).last().unwrap();
+    assert_eq!(last, Err(FatError::ClusterChainLoop));
+    assert!(vol.cluster_chain(10).count() < 200);
+
+    let items: Vec<_> = vol.cluster_chain(60).collect();
+    assert_eq!(items, vec![Ok(60), Err(FatError::ClusterChainLoop)]);
+}
@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use no_std::block::BlockDevice;
+
+pub const RESERVED_SECTORS: u16 = 32;
+pub const FAT_COUNT: u8 = 2;
+pub const ROOT_CLUSTER: u32 = 2;
+
+/// Sparse in-memory block device; sectors never written read back as zeros.
+#[derive(Default, Clone)]
+pub struct MemBlockDevice {
+    sectors: HashMap<u64, [u8; 512]>,
+}
+
+impl MemBlockDevice {
+    pub fn read(&self, lba: u64) -> [u8; 512] {
+        self.sectors.get(&lba).copied().unwrap_or([0u8; 512])
+    }
+
+    pub fn write(&mut self, lba: u64, data: &[u8; 512]) {
+        self.sectors.insert(lba, *data);
+    }
+}
+
+impl BlockDevice for MemBlockDevice {
+    fn read_sector(&self, lba: u64, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.read(lba));
+    }
+
+    fn write_sector(&mut self, lba: u64, buf: &[u8]) {
+        self.sectors.insert(lba, buf.try_into().unwrap());
+    }
+}
+
+/// Wraps a [`MemBlockDevice`], counting reads and writes of the sectors in
+/// a range. The counters stay readable while a volume owns the device.
+#[derive(Clone)]
+pub struct CountingDevice {
+    pub inner: MemBlockDevice,
+    counted: Range<u64>,
+    pub reads: Rc<Cell<usize>>,
+    pub writes: Rc<Cell<usize>>,
+}
+
+impl CountingDevice {
+    pub fn new(inner: MemBlockDevice, counted: Range<u64>) -> Self {
+        Self { inner, counted, reads: Rc::default(), writes: Rc::default() }
+    }
+}
+
+impl BlockDevice for CountingDevice {
+    fn read_sector(&self, lba: u64, buf: &mut [u8]) {
+        if self.counted.contains(&lba) {
+            self.reads.set(self.reads.get() + 1);
+        }
+        self.inner.read_sector(lba, buf)
+    }
+
+    fn write_sector(&mut self, lba: u64, buf: &[u8]) {
+        if self.counted.contains(&lba) {
+            self.writes.set(self.writes.get() + 1);
+        }
+        self.inner.write_sector(lba, buf)
+    }
+}
+
+/// Geometry of a volume produced by [`format`].
+pub struct Layout {
+    pub total_sectors: u32,
+    pub sectors_per_cluster: u8,
+    pub fat_size_sectors: u32,
+}
+
+impl Layout {
+    pub fn first_data_sector(&self) -> u64 {
+        RESERVED_SECTORS as u64 + FAT_COUNT as u64 * self.fat_size_sectors as u64
+    }
+
+    pub fn cluster_lba(&self, cluster: u32) -> u64 {
+        self.first_data_sector() + (cluster - 2) as u64 * self.sectors_per_cluster as u64
+    }
+
+    /// Sectors of the first FAT.
+    pub fn first_fat(&self) -> Range<u64> {
+        RESERVED_SECTORS as u64..RESERVED_SECTORS as u64 + self.fat_size_sectors as u64
+    }
+
+    /// Sectors of every FAT copy.
+    pub fn all_fats(&self) -> Range<u64> {
+        RESERVED_SECTORS as u64..self.first_data_sector()
+    }
+
+    pub fn cluster_count(&self) -> u32 {
+        ((self.total_sectors as u64 - self.first_data_sector()) / self.sectors_per_cluster as u64) as u32
+    }
+}
+
+/// Format a FAT32 volume the way mkfs would: boot sector, FSInfo, backup
+/// boot sector, two FATs and an empty root directory in cluster 2.
+pub fn format(total_sectors: u32, sectors_per_cluster: u8) -> (MemBlockDevice, Layout) {
+    // Microsoft's FAT size formula (FAT32 variant).
+    let tmp1 = total_sectors - RESERVED_SECTORS as u32;
+    let tmp2 = (256 * sectors_per_cluster as u32 + FAT_COUNT as u32) / 2;
+    let fat_size_sectors = tmp1.div_ceil(tmp2);
+
+    let layout = Layout { total_sectors, sectors_per_cluster, fat_size_sectors };
+    let mut dev = MemBlockDevice::default();
+
+    let mut boot = [0u8; 512];
+    boot[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+    boot[3..11].copy_from_slice(b"MSWIN4.1");
+    boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+    boot[13] = sectors_per_cluster;
+    boot[14..16].copy_from_slice(&RESERVED_SECTORS.to_le_bytes());
+    boot[16] = FAT_COUNT;
+    boot[21] = 0xF8;
+    boot[24..26].copy_from_slice(&63u16.to_le_bytes());
+    boot[26..28].copy_from_slice(&255u16.to_le_bytes());
+    boot[32..36].copy_from_slice(&total_sectors.to_le_bytes());
+    boot[36..40].copy_from_slice(&fat_size_sectors.to_le_bytes());
+    boot[44..48].copy_from_slice(&ROOT_CLUSTER.to_le_bytes());
+    boot[48..50].copy_from_slice(&1u16.to_le_bytes());
+    boot[50..52].copy_from_slice(&6u16.to_le_bytes());
+    boot[64] = 0x80;
+    boot[66] = 0x29;
+    boot[67..71].copy_from_slice(&0xCAFEBABEu32.to_le_bytes());
+    boot[71..82].copy_from_slice(b"TESTVOLUME ");
+    boot[82..90].copy_from_slice(b"FAT32   ");
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    dev.write(0, &boot);
+    dev.write(6, &boot);
+
+    let mut fsinfo = [0u8; 512];
+    fsinfo[0..4].copy_from_slice(&0x41615252u32.to_le_bytes());
+    fsinfo[484..488].copy_from_slice(&0x61417272u32.to_le_bytes());
+    fsinfo[488..492].copy_from_slice(&(layout.cluster_count() - 1).to_le_bytes());
+    fsinfo[492..496].copy_from_slice(&3u32.to_le_bytes());
+    fsinfo[508..512].copy_from_slice(&0xAA550000u32.to_le_bytes());
+    dev.write(1, &fsinfo);
+    dev.write(7, &fsinfo);
+
+    let mut fat = [0u8; 512];
+    fat[0..4].copy_from_slice(&0x0FFFFFF8u32.to_le_bytes());
+    fat[4..8].copy_from_slice(&0x0FFFFFFFu32.to_le_bytes());
+    fat[8..12].copy_from_slice(&0x0FFFFFFFu32.to_le_bytes());
+    for copy in 0..FAT_COUNT as u64 {
+        dev.write(RESERVED_SECTORS as u64 + copy * fat_size_sectors as u64, &fat);
+    }
+
+    (dev, layout)
+}
+
+/// A small FAT32 volume with 512-byte clusters.
+pub fn small_volume() -> (MemBlockDevice, Layout) {
+    format(70_000, 1)
+}
+
+/// Read the raw FAT entry of `cluster` from the given FAT copy.
+pub fn fat_entry(dev: &MemBlockDevice, layout: &Layout, copy: u8, cluster: u32) -> u32 {
+    let lba = RESERVED_SECTORS as u64
+        + copy as u64 * layout.fat_size_sectors as u64
+        + cluster as u64 * 4 / 512;
+    let off = (cluster as usize * 4) % 512;
+    let sector = dev.read(lba);
+    u32::from_le_bytes(sector[off..off + 4].try_into().unwrap())
+}
+
+/// Write the raw FAT entry of `cluster` in every FAT copy.
+pub fn set_fat_entry(dev: &mut MemBlockDevice, layout: &Layout, cluster: u32, value: u32) {
+    for copy in 0..FAT_COUNT {
+        let lba = RESERVED_SECTORS as u64
+            + copy as u64 * layout.fat_size_sectors as u64
+            + cluster as u64 * 4 / 512;
+        let off = (cluster as usize * 4) % 512;
+        let mut sector = dev.read(lba);
+        sector[off..off + 4].copy_from_slice(&value.to_le_bytes());
+        dev.write(lba, &sector);
+    }
+}
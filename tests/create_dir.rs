@@ -0,0 +1,64 @@
+mod common;
+
+use common::small_volume;
+use no_std::directory::DirEntry;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_dir, create_dir_all, create_file};
+
+fn entries(vol: &Fat32Volume<common::MemBlockDevice>, cluster: u32) -> Vec<DirEntry> {
+    vol.read_dir(cluster).collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn creates_directory_with_dot_entries() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    let logs = create_dir(&mut vol, 2, "Logs").unwrap();
+    let entry = vol.open_path("/logs").unwrap();
+    assert!(entry.is_dir());
+    assert_eq!(entry.first_cluster, logs);
+    assert_eq!(entry.size, 0);
+    assert_eq!(entry.to_string(), "Logs");
+
+    let dots = entries(&vol, logs);
+    assert_eq!(dots.len(), 2);
+    assert_eq!(&dots[0].name, b".          ");
+    assert_eq!(dots[0].first_cluster, logs);
+    assert_eq!(&dots[1].name, b"..         ");
+    assert_eq!(dots[1].first_cluster, 0);
+    assert!(dots.iter().all(DirEntry::is_dir));
+
+    let day = create_dir(&mut vol, logs, "2024-07-14").unwrap();
+    let dots = entries(&vol, day);
+    assert_eq!(dots[0].first_cluster, day);
+    assert_eq!(dots[1].first_cluster, logs);
+
+    create_file(&mut vol, day, "boot.log", b"ok").unwrap();
+    assert_eq!(vol.open_path("/Logs/2024-07-14/../2024-07-14/boot.log").unwrap().size, 2);
+    assert_eq!(vol.open_path("/Logs/2024-07-14/../..").unwrap().first_cluster, 2);
+
+    assert_eq!(create_dir(&mut vol, 2, "LOGS").unwrap_err(), FatError::AlreadyExists);
+}
+
+#[test]
+fn creates_intermediate_directories() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    let leaf = create_dir_all(&mut vol, "/data/sensors/imu").unwrap();
+    assert_eq!(vol.open_path("/DATA/SENSORS/IMU").unwrap().first_cluster, leaf);
+
+    // existing components are reused
+    let free = vol.space_info(false).unwrap().free_clusters;
+    assert_eq!(create_dir_all(&mut vol, "data\\sensors\\imu\\").unwrap(), leaf);
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, free);
+
+    let gps = create_dir_all(&mut vol, "/data/sensors/imu/../gps").unwrap();
+    assert_eq!(vol.open_path("/data/sensors/gps").unwrap().first_cluster, gps);
+
+    let sensors = vol.open_path("/data/sensors").unwrap().first_cluster;
+    create_file(&mut vol, sensors, "cal.bin", b"").unwrap();
+    assert_eq!(create_dir_all(&mut vol, "/data/sensors/cal.bin/x").unwrap_err(), FatError::NotADirectory);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{fat_entry, small_volume};
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_dir, create_file, create_file_with, IfExists};
+
+#[test]
+fn rejects_duplicate_names() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "HELLO.TXT", b"one").unwrap();
+    assert_eq!(create_file(&mut vol, 2, "HELLO.TXT", b"two").unwrap_err(), FatError::AlreadyExists);
+    assert_eq!(create_file(&mut vol, 2, "hello.txt", b"two").unwrap_err(), FatError::AlreadyExists);
+
+    create_file(&mut vol, 2, "Quarterly report.pdf", b"pdf").unwrap();
+    assert_eq!(
+        create_file(&mut vol, 2, "QUARTERLY REPORT.PDF", b"x").unwrap_err(),
+        FatError::AlreadyExists
+    );
+    // the generated alias counts as a name too
+    assert_eq!(create_file(&mut vol, 2, "quarte~1.pdf", b"x").unwrap_err(), FatError::AlreadyExists);
+
+    assert_eq!(vol.read_dir(2).count(), 2);
+}
+
+#[test]
+fn replaces_contents_and_frees_old_chain() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+
+    create_file(&mut vol, 2, "Config file.ini", &[b'a'; 1500]).unwrap();
+    let old = vol.open_path("/config file.ini").unwrap();
+    let old_chain: Vec<u32> = vol.cluster_chain(old.first_cluster).map(Result::unwrap).collect();
+
+    create_file_with(&mut vol, 2, "CONFIG FILE.INI", b"mode=fast", IfExists::Replace).unwrap();
+
+    let entries: Vec<_> = vol.read_dir(2).map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].to_string(), "Config file.ini");
+    assert_eq!(entries[0].location, old.location);
+    assert_eq!(entries[0].size, 9);
+    let mut buf = [0u8; 9];
+    vol.load_file("/config file.ini", &mut buf).unwrap();
+    assert_eq!(&buf, b"mode=fast");
+
+    let new_cluster = entries[0].first_cluster;
+    for cluster in old_chain.into_iter().filter(|&c| c != new_cluster) {
+        assert_eq!(fat_entry(vol.device(), &layout, 0, cluster), 0);
+        assert_eq!(fat_entry(vol.device(), &layout, 1, cluster), 0);
+    }
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 1));
+
+    // replacing with nothing leaves an empty file without clusters
+    create_file_with(&mut vol, 2, "config file.ini", b"", IfExists::Replace).unwrap();
+    let entry = vol.open_path("/config file.ini").unwrap();
+    assert_eq!((entry.size, entry.first_cluster), (0, 0));
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free));
+}
+
+#[test]
+fn replace_refuses_directories() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_dir(&mut vol, 2, "logs").unwrap();
+    assert_eq!(create_file(&mut vol, 2, "LOGS", b"x").unwrap_err(), FatError::AlreadyExists);
+    assert_eq!(
+        create_file_with(&mut vol, 2, "logs", b"x", IfExists::Replace).unwrap_err(),
+        FatError::IsADirectory
+    );
+    create_file_with(&mut vol, 2, "new.txt", b"x", IfExists::Replace).unwrap();
+    assert_eq!(vol.open_path("/new.txt").unwrap().size, 1);
+}
@@ -4,10 +4,10 @@ use std::path::Path;
 
 const IMG_SIZE_MB: u64 = 64;
 const SECTOR_SIZE: u16 = 512;
-const SECTORS_PER_CLUSTER: u8 = 8;
+const SECTORS_PER_CLUSTER: u8 = 1;
 const RESERVED_SECTORS: u16 = 32;
 const FAT_COUNT: u8 = 2;
-const FAT_SIZE_SECTORS: u32 = 128;
+const FAT_SIZE_SECTORS: u32 = 1009;
 const ROOT_CLUSTER: u32 = 2;
 
 #[test]
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{set_fat_entry, small_volume};
+use no_std::block::BlockDevice;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+#[test]
+fn grows_root_directory_past_its_first_cluster() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    for i in 0..200 {
+        create_file(&mut vol, 2, &format!("LOG{i:03}.TXT"), format!("{i}").as_bytes()).unwrap();
+    }
+
+    // 16 slots per 512-byte cluster
+    let chain: Vec<u32> = vol.cluster_chain(2).map(Result::unwrap).collect();
+    assert_eq!(chain.len(), 13);
+    assert_eq!(vol.read_dir(2).count(), 200);
+    assert_eq!(vol.open_path("/LOG199.TXT").unwrap().size, 3);
+
+    let mut buf = [0u8; 3];
+    assert_eq!(vol.load_file("/LOG150.TXT", &mut buf).unwrap(), 3);
+    assert_eq!(&buf, b"150");
+}
+
+#[test]
+fn honours_the_65536_entry_limit() {
+    let (mut dev, layout) = small_volume();
+
+    // a directory of 4095 full clusters at 100..4195, one cluster short of the limit
+    let mut full = [0u8; 512];
+    for slot in full.chunks_mut(32) {
+        slot[..11].copy_from_slice(b"FILLER  TXT");
+        slot[11] = 0x20;
+    }
+    let first = 100;
+    let last = first + 4094;
+    for cluster in first..=last {
+        dev.write_sector(layout.cluster_lba(cluster), &full);
+        set_fat_entry(&mut dev, &layout, cluster, if cluster == last { 0x0FFFFFFF } else { cluster + 1 });
+    }
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    for i in 0..16 {
+        create_file(&mut vol, first, &format!("LAST{i}.TXT"), b"").unwrap();
+    }
+    assert_eq!(vol.cluster_chain(first).count(), 4096);
+
+    let free = vol.space_info(false).unwrap().free_clusters;
+    assert_eq!(create_file(&mut vol, first, "ONEMORE.TXT", b"x").unwrap_err(), FatError::NoFreeDirectoryEntry);
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, free);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use common::{fat_entry, small_volume, CountingDevice, Layout, MemBlockDevice, FAT_COUNT};
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, remove_file, OpenOptions};
+
+/// Open `dev`, counting writes to any FAT copy.
+fn counting(dev: MemBlockDevice, layout: &Layout) -> (Fat32Volume<CountingDevice>, Rc<Cell<usize>>) {
+    let dev = CountingDevice::new(dev, layout.all_fats());
+    let writes = dev.writes.clone();
+    (Fat32Volume::open(dev).unwrap(), writes)
+}
+
+#[test]
+fn writes_each_fat_sector_once_per_copy() {
+    let (dev, layout) = small_volume();
+    let (mut vol, writes) = counting(dev, &layout);
+
+    // 2000 clusters of 512 bytes span 16 FAT sectors
+    create_file(&mut vol, 2, "big.bin", &vec![5u8; 2000 * 512]).unwrap();
+    vol.sync().unwrap();
+
+    let first = vol.open_path("/big.bin").unwrap().first_cluster;
+    let chain: Vec<u32> = vol.cluster_chain(first).map(Result::unwrap).collect();
+    assert_eq!(chain.len(), 2000);
+    let sectors = (chain[1999] / 128 - chain[0] / 128 + 1) as usize;
+    assert!(writes.get() <= sectors * FAT_COUNT as usize, "{} FAT writes", writes.get());
+
+    let dev = vol.unmount().unwrap().inner;
+    for &cluster in &chain {
+        assert_eq!(fat_entry(&dev, &layout, 0, cluster), fat_entry(&dev, &layout, 1, cluster));
+    }
+    assert_eq!(fat_entry(&dev, &layout, 1, chain[1999]), 0x0FFFFFFF);
+}
+
+#[test]
+fn chain_reaches_disk_before_its_entry() {
+    let (dev, layout) = small_volume();
+    let (mut vol, writes) = counting(dev, &layout);
+
+    create_file(&mut vol, 2, "a.txt", &[1u8; 1024]).unwrap();
+    assert_eq!(writes.get(), FAT_COUNT as usize);
+    // an image taken without syncing already has a valid chain
+    let unsynced = Fat32Volume::open(vol.device().inner.clone()).unwrap();
+    let first = unsynced.open_path("/a.txt").unwrap().first_cluster;
+    assert_eq!(unsynced.cluster_chain(first).collect::<Result<Vec<_>, _>>().unwrap().len(), 2);
+
+    // growth through a handle stays cached until the entry is updated
+    let mut file = OpenOptions::new().append(true).open(&mut vol, "/a.txt").unwrap();
+    file.write(&[2u8; 1024]).unwrap();
+    assert_eq!(file.len(), 2048);
+    assert_eq!(writes.get(), FAT_COUNT as usize);
+    file.close().unwrap();
+    assert_eq!(writes.get(), 2 * FAT_COUNT as usize);
+    assert_eq!(vol.cluster_chain(first).count(), 4);
+
+    vol.sync().unwrap();
+    assert_eq!(writes.get(), 2 * FAT_COUNT as usize);
+
+    remove_file(&mut vol, "/a.txt").unwrap();
+    let dev = vol.device().inner.clone();
+    assert_eq!(fat_entry(&dev, &layout, 0, first), 0);
+    assert_eq!(fat_entry(&dev, &layout, 1, first), 0);
+}
+
+#[test]
+fn zero_sized_cache_writes_through() {
+    let (dev, layout) = small_volume();
+    let (mut vol, writes) = counting(dev, &layout);
+
+    create_file(&mut vol, 2, "a.txt", b"cached").unwrap();
+    vol.set_fat_cache_size(0);
+    assert_eq!(writes.get(), FAT_COUNT as usize);
+
+    create_file(&mut vol, 2, "b.txt", b"direct").unwrap();
+    let first = vol.open_path("/b.txt").unwrap().first_cluster;
+    assert_eq!(fat_entry(&vol.device().inner, &layout, 1, first), 0x0FFFFFFF);
+}
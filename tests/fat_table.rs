@@ -0,0 +1,95 @@
+mod common;
+
+use common::{fat_entry, set_fat_entry, small_volume};
+use no_std::error::FatError;
+use no_std::fat::FatEntry;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, remove_file};
+
+#[test]
+fn decodes_entries() {
+    assert_eq!(FatEntry::from_raw(0), FatEntry::Free);
+    assert_eq!(FatEntry::from_raw(0xF000_0000), FatEntry::Free);
+    assert_eq!(FatEntry::from_raw(1), FatEntry::Reserved(1));
+    assert_eq!(FatEntry::from_raw(42), FatEntry::Next(42));
+    assert_eq!(FatEntry::from_raw(0x1000_002A), FatEntry::Next(42));
+    assert_eq!(FatEntry::from_raw(0x0FFF_FFF0), FatEntry::Reserved(0x0FFF_FFF0));
+    assert_eq!(FatEntry::from_raw(0x0FFF_FFF6), FatEntry::Reserved(0x0FFF_FFF6));
+    assert_eq!(FatEntry::from_raw(0x0FFF_FFF7), FatEntry::Bad);
+    assert_eq!(FatEntry::from_raw(0x0FFF_FFF8), FatEntry::EndOfChain);
+    assert_eq!(FatEntry::from_raw(0xFFFF_FFFF), FatEntry::EndOfChain);
+    assert_eq!(FatEntry::EndOfChain.to_raw(), 0x0FFF_FFFF);
+    assert_eq!(FatEntry::Next(0x1000_002A).to_raw(), 42);
+}
+
+#[test]
+fn reads_typed_entries() {
+    let (mut dev, layout) = small_volume();
+    set_fat_entry(&mut dev, &layout, 10, 0x3000_000B);
+    set_fat_entry(&mut dev, &layout, 11, 0x0FFF_FFF8);
+    set_fat_entry(&mut dev, &layout, 12, 0x0FFF_FFF7);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    let fat = vol.fat();
+    assert_eq!(fat.get(2), Ok(FatEntry::EndOfChain));
+    assert_eq!(fat.get(10), Ok(FatEntry::Next(11)));
+    assert_eq!(fat.get(11), Ok(FatEntry::EndOfChain));
+    assert_eq!(fat.get(12), Ok(FatEntry::Bad));
+    assert_eq!(fat.get(13), Ok(FatEntry::Free));
+}
+
+#[test]
+fn rejects_clusters_outside_the_volume() {
+    let (dev, layout) = small_volume();
+    let end = layout.cluster_count() + 2;
+    // the last FAT sector has entries past the last cluster
+    assert!(layout.fat_size_sectors * 128 > end);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    assert_eq!(vol.fat().get(0), Err(FatError::InvalidCluster));
+    assert_eq!(vol.fat().get(1), Err(FatError::InvalidCluster));
+    assert_eq!(vol.fat().get(end - 1), Ok(FatEntry::Free));
+    assert_eq!(vol.fat().get(end), Err(FatError::InvalidCluster));
+
+    let mut fat = vol.fat_mut();
+    assert_eq!(fat.set(end, FatEntry::EndOfChain), Err(FatError::InvalidCluster));
+    assert_eq!(fat.set(10, FatEntry::Next(end)), Err(FatError::InvalidCluster));
+    assert_eq!(fat.set(10, FatEntry::Next(1)), Err(FatError::InvalidCluster));
+    assert_eq!(fat.get(10), Ok(FatEntry::Free));
+}
+
+#[test]
+fn writes_preserve_reserved_bits() {
+    let (mut dev, layout) = small_volume();
+    set_fat_entry(&mut dev, &layout, 3, 0xA000_0000);
+    set_fat_entry(&mut dev, &layout, 4, 0x5000_0000);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "a.bin", &[1u8; 1024]).unwrap();
+    vol.sync().unwrap();
+    for copy in 0..2 {
+        assert_eq!(fat_entry(vol.device(), &layout, copy, 3), 0xA000_0004);
+        assert_eq!(fat_entry(vol.device(), &layout, copy, 4), 0x5FFF_FFFF);
+    }
+
+    remove_file(&mut vol, "/a.bin").unwrap();
+    vol.sync().unwrap();
+    assert_eq!(fat_entry(vol.device(), &layout, 0, 3), 0xA000_0000);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, 4), 0x5000_0000);
+}
+
+#[test]
+fn set_keeps_free_count_in_step() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+
+    let mut fat = vol.fat_mut();
+    assert_eq!(fat.set(100, FatEntry::Bad), Ok(FatEntry::Free));
+    assert_eq!(fat.set(100, FatEntry::EndOfChain), Ok(FatEntry::Bad));
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 1));
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, free - 1);
+
+    assert_eq!(vol.fat_mut().set(100, FatEntry::Free), Ok(FatEntry::EndOfChain));
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free));
+}
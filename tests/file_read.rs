@@ -0,0 +1,115 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use common::{small_volume, CountingDevice};
+use no_std::error::FatError;
+use no_std::file::SeekFrom;
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+fn volume_with_file(data: &[u8]) -> (Fat32Volume<CountingDevice>, Rc<Cell<usize>>) {
+    let (dev, _) = small_volume();
+    // count every sector read to check that sequential reads reuse the cursor
+    let dev = CountingDevice::new(dev, 0..u64::MAX);
+    let reads = dev.reads.clone();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let root = vol.root_cluster();
+    create_file(&mut vol, root, "DATA.BIN", data).unwrap();
+    (vol, reads)
+}
+
+#[test]
+fn reads_whole_file_in_odd_chunks() {
+    let data = pattern(5000);
+    let (vol, _) = volume_with_file(&data);
+    let entry = vol.open_path("/DATA.BIN").unwrap();
+    let mut file = vol.open_file(&entry).unwrap();
+    assert_eq!(file.len(), 5000);
+
+    let mut out = Vec::new();
+    let mut chunk = [0u8; 333];
+    loop {
+        let n = file.read(&mut chunk).unwrap();
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&chunk[..n]);
+    }
+    assert_eq!(out, data);
+    assert_eq!(file.position(), 5000);
+}
+
+#[test]
+fn sequential_reads_do_not_rewalk_the_chain() {
+    let data = pattern(512 * 200);
+    let (vol, reads) = volume_with_file(&data);
+    let entry = vol.open_path("/DATA.BIN").unwrap();
+    let mut file = vol.open_file(&entry).unwrap();
+
+    reads.set(0);
+    let mut sector = [0u8; 512];
+    for i in 0..200 {
+        assert_eq!(file.read(&mut sector).unwrap(), 512);
+        assert_eq!(&sector[..], &data[i * 512..(i + 1) * 512]);
+    }
+    // a constant number of FAT reads per cluster, not a walk from the start
+    assert!(reads.get() <= 200 * 3, "{}", reads.get());
+}
+
+#[test]
+fn reads_within_a_cluster_skip_the_fat() {
+    // 4 KiB clusters; enough of them to be FAT32
+    let (dev, layout) = common::format(600_000, 8);
+    let data = pattern(4 * 4096);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "DATA.BIN", &data).unwrap();
+
+    let dev = CountingDevice::new(vol.unmount().unwrap(), layout.first_fat());
+    let reads = dev.reads.clone();
+    let vol = Fat32Volume::open(dev).unwrap();
+    let entry = vol.open_path("/DATA.BIN").unwrap();
+    let mut file = vol.open_file(&entry).unwrap();
+
+    reads.set(0);
+    let mut sector = [0u8; 512];
+    for i in 0..32 {
+        assert_eq!(file.read(&mut sector).unwrap(), 512);
+        assert_eq!(&sector[..], &data[i * 512..(i + 1) * 512]);
+    }
+    // one FAT lookup per cluster, none per sector
+    assert!(reads.get() <= 4, "{} FAT reads", reads.get());
+}
+
+#[test]
+fn seeks_relative_to_start_end_and_current() {
+    let data = pattern(3000);
+    let (vol, _) = volume_with_file(&data);
+    let entry = vol.open_path("/DATA.BIN").unwrap();
+    let mut file = vol.open_file(&entry).unwrap();
+    let mut buf = [0u8; 4];
+
+    assert_eq!(file.seek(SeekFrom::Start(1500)).unwrap(), 1500);
+    file.read(&mut buf).unwrap();
+    assert_eq!(buf, data[1500..1504]);
+
+    assert_eq!(file.seek(SeekFrom::Current(-1000)).unwrap(), 504);
+    file.read(&mut buf).unwrap();
+    assert_eq!(buf, data[504..508]);
+
+    assert_eq!(file.seek(SeekFrom::End(-2)).unwrap(), 2998);
+    assert_eq!(file.read(&mut buf).unwrap(), 2);
+    assert_eq!(buf[..2], data[2998..]);
+
+    assert_eq!(file.seek(SeekFrom::End(10)).unwrap(), 3010);
+    assert_eq!(file.read(&mut buf).unwrap(), 0);
+    assert_eq!(file.seek(SeekFrom::Current(-4000)).unwrap_err(), FatError::InvalidSeek);
+
+    let root = vol.open_path("/").unwrap();
+    assert_eq!(vol.open_file(&root).err(), Some(FatError::IsADirectory));
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::small_volume;
+use no_std::error::FatError;
+use no_std::file::SeekFrom;
+use no_std::time::{DateTime, TimeSource};
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, OpenOptions};
+
+struct Clock;
+
+impl TimeSource for Clock {
+    fn now(&self) -> DateTime {
+        DateTime { year: 2024, month: 7, day: 14, hour: 9, minute: 30, second: 10, millis: 0 }
+    }
+}
+
+fn read_all<T: TimeSource>(vol: &Fat32Volume<common::MemBlockDevice, T>, path: &str) -> Vec<u8> {
+    let mut buf = vec![0u8; vol.open_path(path).unwrap().size as usize];
+    vol.load_file(path, &mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn streams_data_into_new_file() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open_with_time_source(dev, Clock).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 7) as u8).collect();
+    let mut file = OpenOptions::new().write(true).create(true).open(&mut vol, "/rec.bin").unwrap();
+    for chunk in data.chunks(333) {
+        assert_eq!(file.write(chunk).unwrap(), chunk.len());
+    }
+    assert_eq!(file.len(), 5000);
+    file.close().unwrap();
+
+    let entry = vol.open_path("/rec.bin").unwrap();
+    assert_eq!(entry.size, 5000);
+    assert_eq!(entry.modified, Clock.now());
+    assert_eq!(vol.cluster_chain(entry.first_cluster).count(), 10);
+    assert_eq!(read_all(&vol, "/rec.bin"), data);
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 10));
+}
+
+#[test]
+fn entry_is_updated_on_flush_and_drop() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&mut vol, "log.txt").unwrap();
+        file.write(b"first line\n").unwrap();
+        file.flush().unwrap();
+    }
+    assert_eq!(vol.open_path("/log.txt").unwrap().size, 11);
+
+    {
+        let mut file = OpenOptions::new().append(true).open(&mut vol, "log.txt").unwrap();
+        file.write(b"second line\n").unwrap();
+    }
+    assert_eq!(read_all(&vol, "/log.txt"), b"first line\nsecond line\n");
+}
+
+#[test]
+fn overwrites_and_seeks_in_place() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "data.bin", &[b'a'; 1024]).unwrap();
+    let first_cluster = vol.open_path("/data.bin").unwrap().first_cluster;
+
+    let mut file = OpenOptions::new().read(true).write(true).open(&mut vol, "/data.bin").unwrap();
+    file.seek(SeekFrom::Start(510)).unwrap();
+    file.write(b"XXXX").unwrap();
+    file.seek(SeekFrom::Current(-6)).unwrap();
+    let mut buf = [0u8; 8];
+    assert_eq!(file.read(&mut buf).unwrap(), 8);
+    assert_eq!(&buf, b"aaXXXXaa");
+
+    // writing nothing past the end leaves the size alone
+    file.seek(SeekFrom::End(2000)).unwrap();
+    assert_eq!(file.write(&[]).unwrap(), 0);
+    assert_eq!(file.len(), 1024);
+
+    // writing past the end zero-fills the gap
+    file.seek(SeekFrom::End(100)).unwrap();
+    file.write(b"end").unwrap();
+    assert_eq!(file.len(), 1127);
+    file.close().unwrap();
+
+    let data = read_all(&vol, "/data.bin");
+    assert_eq!(vol.open_path("/data.bin").unwrap().first_cluster, first_cluster);
+    assert_eq!(&data[508..516], b"aaXXXXaa");
+    assert!(data[1024..1124].iter().all(|&b| b == 0));
+    assert_eq!(&data[1124..], b"end");
+}
+
+#[test]
+fn truncates_and_reopens() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+    create_file(&mut vol, 2, "old.txt", &[1u8; 2000]).unwrap();
+
+    let file = OpenOptions::new().write(true).truncate(true).open(&mut vol, "/old.txt").unwrap();
+    assert!(file.is_empty());
+    drop(file);
+
+    let entry = vol.open_path("/old.txt").unwrap();
+    assert_eq!((entry.size, entry.first_cluster), (0, 0));
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free));
+
+    let mut file = OpenOptions::new().write(true).create(true).open(&mut vol, "/old.txt").unwrap();
+    file.write(b"new").unwrap();
+    file.close().unwrap();
+    assert_eq!(read_all(&vol, "/old.txt"), b"new");
+}
+
+#[test]
+fn enforces_open_modes() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "a.txt", b"abc").unwrap();
+
+    let invalid = FatError::InvalidOpenOptions;
+    assert_eq!(OpenOptions::new().open(&mut vol, "/a.txt").err(), Some(invalid));
+    assert_eq!(OpenOptions::new().read(true).truncate(true).open(&mut vol, "/a.txt").err(), Some(invalid));
+    assert_eq!(OpenOptions::new().read(true).create(true).open(&mut vol, "/a.txt").err(), Some(invalid));
+    assert_eq!(
+        OpenOptions::new().write(true).create_new(true).open(&mut vol, "/A.TXT").err(),
+        Some(FatError::AlreadyExists)
+    );
+    assert_eq!(OpenOptions::new().read(true).open(&mut vol, "/b.txt").err(), Some(FatError::NotFound));
+    assert_eq!(OpenOptions::new().read(true).open(&mut vol, "/").err(), Some(FatError::InvalidFileName));
+
+    let mut file = OpenOptions::new().read(true).open(&mut vol, "/a.txt").unwrap();
+    assert_eq!(file.write(b"x").unwrap_err(), FatError::NotWritable);
+    drop(file);
+    let mut file = OpenOptions::new().write(true).open(&mut vol, "/a.txt").unwrap();
+    assert_eq!(file.read(&mut [0u8; 3]).unwrap_err(), FatError::NotReadable);
+}
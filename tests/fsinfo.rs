@@ -0,0 +1,78 @@
+mod common;
+
+use common::small_volume;
+use no_std::block::BlockDevice;
+use no_std::fsinfo::FsInfo;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, remove_file};
+
+fn on_disk(dev: &impl BlockDevice) -> FsInfo {
+    let mut sector = [0u8; 512];
+    dev.read_sector(1, &mut sector);
+    FsInfo::parse(&sector).unwrap()
+}
+
+#[test]
+fn hints_are_kept_in_memory_until_sync() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let initial = vol.fsinfo().unwrap();
+    assert_eq!(initial, FsInfo { free_count: Some(layout.cluster_count() - 1), next_free: Some(3) });
+
+    create_file(&mut vol, 2, "a.bin", &[1u8; 1500]).unwrap();
+    let last = vol.cluster_chain(vol.open_path("/a.bin").unwrap().first_cluster).last().unwrap().unwrap();
+    let info = vol.fsinfo().unwrap();
+    assert_eq!(info.free_count, initial.free_count.map(|n| n - 3));
+    assert_eq!(info.next_free, Some(last));
+    assert_eq!(on_disk(vol.device()), initial);
+
+    remove_file(&mut vol, "/a.bin").unwrap();
+    assert_eq!(vol.fsinfo().unwrap().free_count, initial.free_count);
+    assert_eq!(vol.fsinfo().unwrap().next_free, Some(last));
+
+    vol.sync().unwrap();
+    assert_eq!(on_disk(vol.device()), vol.fsinfo().unwrap());
+
+    create_file(&mut vol, 2, "b.bin", b"b").unwrap();
+    let expected = vol.fsinfo().unwrap();
+    let dev = vol.unmount().unwrap();
+    assert_eq!(on_disk(&dev), expected);
+    assert_eq!(Fat32Volume::open(dev).unwrap().fsinfo().unwrap(), expected);
+}
+
+#[test]
+fn unknown_free_count_stays_unknown() {
+    let (mut dev, _) = small_volume();
+    let mut sector = [0u8; 512];
+    dev.read_sector(1, &mut sector);
+    sector[488..496].fill(0xFF);
+    dev.write_sector(1, &sector);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    assert_eq!(vol.fsinfo().unwrap(), FsInfo { free_count: None, next_free: None });
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    let cluster = vol.open_path("/a.bin").unwrap().first_cluster;
+
+    let dev = vol.unmount().unwrap();
+    dev.read_sector(1, &mut sector);
+    assert_eq!(&sector[488..492], &[0xFF; 4]);
+    assert_eq!(on_disk(&dev), FsInfo { free_count: None, next_free: Some(cluster) });
+}
+
+#[test]
+fn invalid_sector_is_left_alone() {
+    let (mut dev, _) = small_volume();
+    let mut sector = [0u8; 512];
+    dev.read_sector(1, &mut sector);
+    sector[510] = 0;
+    dev.write_sector(1, &sector);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    assert!(vol.fsinfo().is_err());
+    create_file(&mut vol, 2, "a.bin", b"a").unwrap();
+    let dev = vol.unmount().unwrap();
+
+    let mut after = [0u8; 512];
+    dev.read_sector(1, &mut after);
+    assert_eq!(after, sector);
+}
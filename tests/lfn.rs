@@ -0,0 +1,143 @@
+mod common;
+
+use common::small_volume;
+use no_std::block::BlockDevice;
+use no_std::directory::DirEntry;
+use no_std::lfn::checksum;
+use no_std::volume::Fat32Volume;
+
+const OFFSETS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Build the LFN slots for `name` in on-disk order.
+fn lfn_slots(name: &str, short: &[u8; 11]) -> Vec<[u8; 32]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    let count = units.len().div_ceil(13);
+    if !units.len().is_multiple_of(13) {
+        units.push(0);
+    }
+    units.resize(count * 13, 0xFFFF);
+
+    (1..=count)
+        .rev()
+        .map(|seq| {
+            let mut slot = [0u8; 32];
+            slot[0] = seq as u8 | if seq == count { 0x40 } else { 0 };
+            slot[11] = 0x0F;
+            slot[13] = checksum(short);
+            for (i, &off) in OFFSETS.iter().enumerate() {
+                let unit = units[(seq - 1) * 13 + i];
+                slot[off..off + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            slot
+        })
+        .collect()
+}
+
+fn short_entry(short: &[u8; 11]) -> [u8; 32] {
+    let mut slot = [0u8; 32];
+    slot[0..11].copy_from_slice(short);
+    slot[11] = 0x20;
+    slot
+}
+
+fn list(slots: &[[u8; 32]]) -> Vec<DirEntry> {
+    let (mut dev, layout) = small_volume();
+    // spread the slots over the first two sectors of the root cluster chain
+    let mut bytes = slots.concat();
+    bytes.resize(1024, 0);
+    dev.write_sector(layout.cluster_lba(2), &bytes[..512]);
+    dev.write_sector(layout.cluster_lba(3), &bytes[512..]);
+    common::set_fat_entry(&mut dev, &layout, 2, 3);
+    common::set_fat_entry(&mut dev, &layout, 3, 0x0FFFFFFF);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    vol.read_dir(2).collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn decodes_long_names_across_clusters() {
+    let short = *b"FIRMWA~1BIN";
+    let mut slots = vec![short_entry(b"PADDING    "); 13];
+    slots.extend(lfn_slots("firmware-update-für-gerät.bin", &short));
+    slots.push(short_entry(&short));
+
+    let entries = list(&slots);
+    assert_eq!(entries.len(), 14);
+    let entry = &entries[13];
+    assert_eq!(entry.to_string(), "firmware-update-für-gerät.bin");
+    assert_eq!(entries[0].to_string(), "PADDING");
+    assert!(entries[0].long_name.is_none());
+}
+
+#[test]
+fn exact_multiple_of_thirteen_has_no_terminator() {
+    let short = *b"ABCDEF~1   ";
+    let mut slots = lfn_slots("abcdefghijklm", &short);
+    slots.push(short_entry(&short));
+
+    assert_eq!(list(&slots)[0].to_string(), "abcdefghijklm");
+}
+
+#[test]
+fn falls_back_to_short_name() {
+    let short = *b"LONGNA~1TXT";
+
+    // checksum computed for a different short name
+    let mut slots = lfn_slots("long name.txt", b"OTHER   TXT");
+    slots.push(short_entry(&short));
+    // orphaned chain: middle slot missing
+    let mut orphan = lfn_slots("a much longer name than thirteen units.txt", &short);
+    orphan.remove(1);
+    slots.extend(orphan);
+    slots.push(short_entry(&short));
+    // chain interrupted by a deleted slot
+    let mut deleted = lfn_slots("long name.txt", &short);
+    deleted.push({
+        let mut slot = short_entry(b"GONE    TXT");
+        slot[0] = 0xE5;
+        slot
+    });
+    slots.extend(deleted);
+    slots.push(short_entry(&short));
+
+    let entries = list(&slots);
+    assert_eq!(entries.len(), 3);
+    for entry in &entries {
+        assert!(entry.long_name.is_none());
+        assert_eq!(entry.to_string(), "LONGNA~1.TXT");
+    }
+}
+
+#[test]
+fn rejects_slots_with_sequence_zero() {
+    let short = *b"LONGNA~1TXT";
+    let mut slots = Vec::new();
+    for order in [0x20, 0x80] {
+        slots.extend(lfn_slots("long name.txt", &short));
+        // stray slot after a complete chain, with a matching checksum
+        let mut stray = lfn_slots("long name.txt", &short)[0];
+        stray[0] = order;
+        slots.push(stray);
+        slots.push(short_entry(&short));
+    }
+
+    let entries = list(&slots);
+    assert_eq!(entries.len(), 2);
+    for entry in &entries {
+        assert!(entry.long_name.is_none());
+    }
+}
+
+#[test]
+fn checksum_uses_stored_0x05_lead_byte() {
+    // a short name starting with 0xE5 is stored with 0x05 in its place
+    let stored = *b"\x05AB     TXT";
+    let mut slots = lfn_slots("long.txt", &stored);
+    slots.push(short_entry(&stored));
+
+    let entries = list(&slots);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name[0], 0xE5);
+    assert_eq!(entries[0].to_string(), "long.txt");
+    assert_eq!(entries[0].location.unwrap().lfn_slots, 1);
+}
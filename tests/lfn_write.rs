@@ -0,0 +1,99 @@
+mod common;
+
+use common::small_volume;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+fn short_name(vol: &Fat32Volume<common::MemBlockDevice>, path: &str) -> String {
+    let entry = vol.open_path(path).unwrap();
+    entry.short_name_chars().collect()
+}
+
+#[test]
+fn writes_long_names_with_numeric_tails() {
+    let (dev, _) = common::format(600_000, 8);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    for i in 0..7 {
+        let name = format!("firmware-update-{i}.bin");
+        create_file(&mut vol, 2, &name, name.as_bytes()).unwrap();
+    }
+
+    for i in 0..4 {
+        let name = format!("firmware-update-{i}.bin");
+        let entry = vol.open_path(&name).unwrap();
+        assert_eq!(entry.to_string(), name);
+        assert_eq!(short_name(&vol, &name), format!("FIRMWA~{}.BIN", i + 1));
+    }
+
+    // past ~4, aliases switch to two base characters, a hash and a tail
+    let hashed: Vec<String> = (4..7).map(|i| short_name(&vol, &format!("firmware-update-{i}.bin"))).collect();
+    for (i, alias) in hashed.iter().enumerate() {
+        assert_eq!(alias.len(), 12, "{alias}");
+        assert!(alias.starts_with("FI"), "{alias}");
+        assert!(alias.ends_with(".BIN"), "{alias}");
+        assert!(alias[2..6].chars().all(|c| c.is_ascii_hexdigit()), "{alias}");
+        assert_eq!(&alias[6..7], "~");
+        // each alias resolves back to its own file
+        let entry = vol.open_path(alias).unwrap();
+        assert_eq!(entry.to_string(), format!("firmware-update-{}.bin", i + 4));
+    }
+}
+
+#[test]
+fn keeps_short_names_when_lossless() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "README.TXT", b"1").unwrap();
+    create_file(&mut vol, 2, "notes.md", b"2").unwrap();
+    create_file(&mut vol, 2, "my file.txt", b"3").unwrap();
+    create_file(&mut vol, 2, ".profile", b"4").unwrap();
+    create_file(&mut vol, 2, "a+b.c", b"5").unwrap();
+
+    let readme = vol.open_path("/readme.txt").unwrap();
+    assert!(readme.long_name.is_none());
+
+    let notes = vol.open_path("/NOTES.MD").unwrap();
+    assert_eq!(notes.to_string(), "notes.md");
+    assert_eq!(short_name(&vol, "notes.md"), "NOTES.MD");
+
+    assert_eq!(short_name(&vol, "my file.txt"), "MYFILE~1.TXT");
+    assert_eq!(short_name(&vol, ".profile"), "PROFIL~1");
+    assert_eq!(short_name(&vol, "a+b.c"), "A_B~1.C");
+}
+
+#[test]
+fn rejects_invalid_names_before_allocating() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.space_info(false).unwrap().free_clusters;
+
+    let too_long = "x".repeat(256);
+    for name in ["", " ", "..", "a/b", "what?", "tab\there", &too_long] {
+        assert_eq!(create_file(&mut vol, 2, name, b"data").unwrap_err(), FatError::InvalidFileName, "{name:?}");
+    }
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, free);
+
+    // trailing spaces and periods are ignored, as on Windows
+    create_file(&mut vol, 2, "trailing. . ", b"x").unwrap();
+    assert_eq!(vol.open_path("trailing").unwrap().to_string(), "trailing");
+}
+
+#[test]
+fn entry_set_stays_contiguous_across_clusters() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    // the root directory is a single 16-slot cluster: fill 14 slots
+    for i in 0..14 {
+        create_file(&mut vol, 2, &format!("F{i}.TXT"), b"").unwrap();
+    }
+
+    // two LFN slots plus the short entry: the set continues in a new cluster
+    let long = "a name spanning two lfn slots.txt";
+    create_file(&mut vol, 2, long, b"data").unwrap();
+    assert_eq!(vol.cluster_chain(2).count(), 2);
+    assert_eq!(vol.open_path(long).unwrap().to_string(), long);
+    assert_eq!(vol.read_dir(2).count(), 15);
+}
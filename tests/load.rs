@@ -0,0 +1,40 @@
+mod common;
+
+use common::small_volume;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+#[test]
+fn loads_file_into_caller_buffer() {
+    let data: Vec<u8> = (0..1300u32).map(|i| i as u8).collect();
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "KERNEL.BIN", &data).unwrap();
+
+    let mut buf = [0u8; 2048];
+    assert_eq!(vol.load_file("/kernel.bin", &mut buf).unwrap(), 1300);
+    assert_eq!(&buf[..1300], &data[..]);
+
+    let mut small = [0u8; 1000];
+    assert_eq!(vol.load_file("/KERNEL.BIN", &mut small).unwrap_err(), FatError::BufferTooSmall);
+    assert_eq!(vol.load_file("/MISSING.BIN", &mut buf).unwrap_err(), FatError::NotFound);
+
+    let first_cluster = vol.open_path("/KERNEL.BIN").unwrap().first_cluster;
+    let mut sectors = Vec::new();
+    let mut streamed = Vec::new();
+    let loaded = vol
+        .load_file_with("/KERNEL.BIN", |lba, bytes| {
+            sectors.push((lba, bytes.len()));
+            streamed.extend_from_slice(bytes);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(loaded, 1300);
+    assert_eq!(streamed, data);
+    let lba = layout.cluster_lba(first_cluster);
+    assert_eq!(sectors, vec![(lba, 512), (lba + 1, 512), (lba + 2, 276)]);
+
+    let aborted = vol.load_file_with("/KERNEL.BIN", |_, _| Err(FatError::IoError));
+    assert_eq!(aborted.unwrap_err(), FatError::IoError);
+}
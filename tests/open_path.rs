@@ -0,0 +1,110 @@
+mod common;
+
+use common::{set_fat_entry, small_volume, Layout, MemBlockDevice};
+use no_std::block::BlockDevice;
+use no_std::directory::ATTR_DIRECTORY;
+use no_std::error::FatError;
+use no_std::lfn::checksum;
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+fn dir_slot(name: &[u8; 11], cluster: u32) -> [u8; 32] {
+    let mut slot = [0u8; 32];
+    slot[0..11].copy_from_slice(name);
+    slot[11] = ATTR_DIRECTORY;
+    slot[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    slot[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+    slot
+}
+
+fn write_dir(dev: &mut MemBlockDevice, layout: &Layout, cluster: u32, slots: &[[u8; 32]]) {
+    let mut bytes = slots.concat();
+    bytes.resize(512, 0);
+    dev.write_sector(layout.cluster_lba(cluster), &bytes);
+    set_fat_entry(dev, layout, cluster, 0x0FFFFFFF);
+}
+
+/// /EFI/BOOT/BOOTX64.EFI, /README.TXT and "/Boot Loader" (long name only)
+fn tree() -> Fat32Volume<MemBlockDevice> {
+    let (mut dev, layout) = small_volume();
+
+    let short = *b"BOOTLO~1   ";
+    let mut lfn = [0xFFu8; 32];
+    lfn[0] = 0x41;
+    lfn[11] = 0x0F;
+    lfn[12] = 0;
+    lfn[13] = checksum(&short);
+    lfn[26..28].copy_from_slice(&[0, 0]);
+    let units: Vec<u16> = "Boot Loader".encode_utf16().chain([0]).collect();
+    let offsets = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+    for (unit, off) in units.iter().zip(offsets) {
+        lfn[off..off + 2].copy_from_slice(&unit.to_le_bytes());
+    }
+
+    write_dir(&mut dev, &layout, 2, &[dir_slot(b"EFI        ", 3), lfn, dir_slot(&short, 5)]);
+    write_dir(&mut dev, &layout, 3, &[
+        dir_slot(b".          ", 3),
+        dir_slot(b"..         ", 0),
+        dir_slot(b"BOOT       ", 4),
+    ]);
+    write_dir(&mut dev, &layout, 4, &[dir_slot(b".          ", 4), dir_slot(b"..         ", 3)]);
+    write_dir(&mut dev, &layout, 5, &[dir_slot(b".          ", 5), dir_slot(b"..         ", 0)]);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 4, "BOOTX64.EFI", b"MZ").unwrap();
+    create_file(&mut vol, 2, "README.TXT", b"read me").unwrap();
+    vol
+}
+
+#[test]
+fn resolves_paths() {
+    let vol = tree();
+
+    let file = vol.open_path("/EFI/BOOT/BOOTX64.EFI").unwrap();
+    assert_eq!(file.size, 2);
+    assert!(!file.is_dir());
+
+    for path in [
+        "\\efi\\boot\\bootx64.efi",
+        "efi/Boot//BootX64.Efi",
+        "/EFI/./BOOT/../BOOT/BOOTX64.EFI",
+        "/../EFI/BOOT/BOOTX64.EFI",
+    ] {
+        assert_eq!(vol.open_path(path).unwrap().first_cluster, file.first_cluster, "{path}");
+    }
+
+    let root = vol.open_path("/").unwrap();
+    assert!(root.is_dir());
+    assert_eq!(root.first_cluster, vol.root_cluster());
+    assert_eq!(vol.open_path("/EFI/..").unwrap().first_cluster, vol.root_cluster());
+    assert_eq!(vol.open_path("/EFI/BOOT/..").unwrap().first_cluster, 3);
+
+    let loader = vol.open_path("/boot loader").unwrap();
+    assert_eq!(loader.first_cluster, 5);
+    assert_eq!(loader.to_string(), "Boot Loader");
+    assert_eq!(vol.open_path("/BOOTLO~1").unwrap().first_cluster, 5);
+}
+
+#[test]
+fn distinguishes_not_found_from_not_a_directory() {
+    let vol = tree();
+
+    assert_eq!(vol.open_path("/EFI/MISSING.EFI").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.open_path("/NOPE/BOOTX64.EFI").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.open_path("/README.TXT/X").unwrap_err(), FatError::NotADirectory);
+    assert_eq!(vol.open_path("/README.TXT/..").unwrap_err(), FatError::NotADirectory);
+}
+
+#[test]
+fn dotdot_resolves_to_the_parents_own_entry() {
+    let vol = tree();
+
+    let efi = vol.open_path("/EFI").unwrap();
+    let up = vol.open_path("/EFI/BOOT/..").unwrap();
+    assert_eq!(up.name, efi.name);
+    assert_eq!(up.location, efi.location);
+    assert_eq!(up.to_string(), "EFI");
+
+    let boot = vol.open_path("/EFI/BOOT").unwrap();
+    assert_eq!(vol.open_path("/EFI/BOOT/.").unwrap().location, boot.location);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::small_volume;
+use no_std::block::BlockDevice;
+use no_std::directory::{ATTR_DIRECTORY, DirEntry};
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+#[test]
+fn lists_created_files() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let root = vol.root_cluster();
+
+    create_file(&mut vol, root, "HELLO.TXT", b"hello").unwrap();
+    create_file(&mut vol, root, "DATA.BIN", &[0xAB; 1500]).unwrap();
+
+    let entries: Vec<DirEntry> = vol.read_dir(root).collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(&entries[0].name, b"HELLO   TXT");
+    assert_eq!(entries[0].size, 5);
+    assert_eq!(&entries[1].name, b"DATA    BIN");
+    assert_eq!(entries[1].size, 1500);
+
+    let chain: Vec<u32> = vol.cluster_chain(entries[1].first_cluster).map(Result::unwrap).collect();
+    assert_eq!(chain.len(), 3);
+}
+
+#[test]
+fn skips_deleted_lfn_and_label_slots() {
+    let (mut dev, layout) = small_volume();
+    let root_lba = layout.cluster_lba(2);
+
+    let mut sector = [0u8; 512];
+    sector[0..11].copy_from_slice(b"TESTVOLUME ");
+    sector[11] = 0x08;
+    sector[32] = 0xE5;
+    sector[32 + 1..32 + 11].copy_from_slice(b"OLD    TXT");
+    sector[64] = 0x41;
+    sector[64 + 11] = 0x0F;
+    sector[96..96 + 11].copy_from_slice(b"SUBDIR     ");
+    sector[96 + 11] = ATTR_DIRECTORY;
+    sector[96 + 20..96 + 22].copy_from_slice(&1u16.to_le_bytes());
+    sector[96 + 26..96 + 28].copy_from_slice(&5u16.to_le_bytes());
+    // 0x00 terminator at slot 4, followed by garbage that must be ignored
+    sector[160..171].copy_from_slice(b"GARBAGE    ");
+    dev.write_sector(root_lba, &sector);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    let entries: Vec<DirEntry> = vol.read_dir(0).collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(&entries[0].name, b"SUBDIR     ");
+    assert!(entries[0].is_dir());
+    assert_eq!(entries[0].first_cluster, 0x0001_0005);
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use common::{fat_entry, small_volume, Layout, MemBlockDevice};
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_dir, create_dir_all, create_file, remove_dir, remove_dir_all};
+
+#[test]
+fn removes_empty_directory() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count;
+
+    let logs = create_dir(&mut vol, 2, "Logs").unwrap();
+    create_file(&mut vol, logs, "boot.log", b"ok").unwrap();
+    assert_eq!(remove_dir(&mut vol, "/logs").unwrap_err(), FatError::DirectoryNotEmpty);
+
+    no_std::write::remove_file(&mut vol, "/logs/boot.log").unwrap();
+    remove_dir(&mut vol, "/logs").unwrap();
+
+    assert_eq!(vol.open_path("/logs").unwrap_err(), FatError::NotFound);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, logs), 0);
+    assert_eq!(fat_entry(vol.device(), &layout, 1, logs), 0);
+    assert_eq!(vol.fsinfo().unwrap().free_count, free);
+}
+
+#[test]
+fn refuses_root_files_and_dot_entries() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_dir_all(&mut vol, "/a/b").unwrap();
+    create_file(&mut vol, 2, "file.txt", b"x").unwrap();
+
+    assert_eq!(remove_dir(&mut vol, "/").unwrap_err(), FatError::IsRootDirectory);
+    assert_eq!(remove_dir_all(&mut vol, "/").unwrap_err(), FatError::IsRootDirectory);
+    assert_eq!(remove_dir_all(&mut vol, "/a/..").unwrap_err(), FatError::IsRootDirectory);
+    assert_eq!(remove_dir_all(&mut vol, "/a/b/..").unwrap_err(), FatError::InvalidFileName);
+    assert_eq!(remove_dir(&mut vol, "/file.txt").unwrap_err(), FatError::NotADirectory);
+    assert_eq!(remove_dir_all(&mut vol, "/missing").unwrap_err(), FatError::NotFound);
+    assert!(vol.open_path("/a/b").unwrap().is_dir());
+}
+
+#[test]
+fn removes_tree_depth_first() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count;
+    let scanned = vol.space_info(false).unwrap().free_clusters;
+
+    let imu = create_dir_all(&mut vol, "/data/sensors/imu").unwrap();
+    create_dir_all(&mut vol, "/data/empty").unwrap();
+    create_file(&mut vol, imu, "samples.bin", &[1u8; 2000]).unwrap();
+    let data = vol.open_path("/data").unwrap().first_cluster;
+    for i in 0..20 {
+        create_file(&mut vol, data, &format!("log {i}.txt"), b"entry").unwrap();
+    }
+    create_file(&mut vol, 2, "keep.txt", b"keep").unwrap();
+    let keep = vol.open_path("/keep.txt").unwrap().first_cluster;
+
+    remove_dir_all(&mut vol, "/DATA").unwrap();
+
+    assert_eq!(vol.open_path("/data").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.load_file("/keep.txt", &mut [0u8; 4]).unwrap(), 4);
+    assert_eq!(fat_entry(vol.device(), &layout, 1, imu), 0);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, keep), 0x0FFFFFFF);
+    assert_eq!(vol.fsinfo().unwrap().free_count, free.map(|n| n - 1));
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, scanned - 1);
+}
+
+#[test]
+fn survives_directory_cycles() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let scanned = vol.space_info(false).unwrap().free_clusters;
+
+    let a = create_dir(&mut vol, 2, "a").unwrap();
+    let b = create_dir(&mut vol, a, "b").unwrap();
+    create_dir(&mut vol, b, "to_a").unwrap();
+    create_dir(&mut vol, b, "to_root").unwrap();
+
+    // corrupt the image: point the subdirectories of b back at a and the root
+    let mut dev = vol.device().clone();
+    for (name, target) in [("/a/b/to_a", a), ("/a/b/to_root", 2)] {
+        let entry = vol.open_path(name).unwrap();
+        let old = entry.first_cluster;
+        let pos = entry.location.unwrap().pos;
+        let mut sector = dev.read(pos.lba);
+        sector[pos.offset + 26..pos.offset + 28].copy_from_slice(&(target as u16).to_le_bytes());
+        dev.write(pos.lba, &sector);
+        common::set_fat_entry(&mut dev, &layout, old, 0);
+    }
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, scanned - 2);
+
+    remove_dir_all(&mut vol, "/a").unwrap();
+
+    assert_eq!(vol.open_path("/a").unwrap_err(), FatError::NotFound);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, 2), 0x0FFFFFFF);
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, scanned);
+}
+
+#[test]
+fn ignores_links_to_ancestors_outside_the_tree() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    let outer = create_dir(&mut vol, 2, "outer").unwrap();
+    let inner = create_dir(&mut vol, outer, "inner").unwrap();
+    let link = create_dir(&mut vol, inner, "link").unwrap();
+
+    let mut dev = vol.device().clone();
+    let entry = vol.open_path("/outer/inner/link").unwrap();
+    let pos = entry.location.unwrap().pos;
+    let mut sector = dev.read(pos.lba);
+    sector[pos.offset + 26..pos.offset + 28].copy_from_slice(&(outer as u16).to_le_bytes());
+    dev.write(pos.lba, &sector);
+    common::set_fat_entry(&mut dev, &layout, link, 0);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    remove_dir_all(&mut vol, "/outer/inner").unwrap();
+    assert_eq!(fat_entry(vol.device(), &layout, 0, outer), 0x0FFFFFFF);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, inner), 0);
+    assert!(vol.read_dir(outer).all(|e| e.unwrap().to_string() != "inner"));
+}
+
+/// Point the entry at `path` on `dev` at `cluster`, freeing its old chain.
+fn relink(vol: &Fat32Volume<MemBlockDevice>, dev: &mut MemBlockDevice, layout: &Layout, path: &str, cluster: u32) {
+    let entry = vol.open_path(path).unwrap();
+    let pos = entry.location.unwrap().pos;
+    let mut sector = dev.read(pos.lba);
+    sector[pos.offset + 20..pos.offset + 22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    sector[pos.offset + 26..pos.offset + 28].copy_from_slice(&(cluster as u16).to_le_bytes());
+    dev.write(pos.lba, &sector);
+    common::set_fat_entry(dev, layout, entry.first_cluster, 0);
+}
+
+#[test]
+fn refuses_directory_entry_with_cluster_zero() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_dir(&mut vol, 2, "zero").unwrap();
+    create_file(&mut vol, 2, "precious.txt", b"keep me").unwrap();
+    let precious = vol.open_path("/precious.txt").unwrap().first_cluster;
+
+    vol.sync().unwrap();
+    let mut dev = vol.device().clone();
+    relink(&vol, &mut dev, &layout, "/zero", 0);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let scanned = vol.space_info(false).unwrap().free_clusters;
+
+    assert_eq!(remove_dir(&mut vol, "/zero").unwrap_err(), FatError::IsRootDirectory);
+    assert_eq!(remove_dir_all(&mut vol, "/zero").unwrap_err(), FatError::IsRootDirectory);
+    assert!(vol.open_path("/zero").is_ok());
+    vol.sync().unwrap();
+    assert_eq!(fat_entry(vol.device(), &layout, 0, precious), 0x0FFFFFFF);
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, scanned);
+}
+
+#[test]
+fn never_frees_clusters_of_the_root_chain() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let victim = create_dir(&mut vol, 2, "victim").unwrap();
+    create_dir(&mut vol, victim, "dir_link").unwrap();
+    create_file(&mut vol, victim, "file_link", b"x").unwrap();
+    // fill the root past its first cluster
+    for i in 0..20 {
+        create_file(&mut vol, 2, &format!("f{i}"), b"root").unwrap();
+    }
+    let root: Vec<u32> = vol.cluster_chain(2).map(Result::unwrap).collect();
+    assert!(root.len() > 1);
+
+    vol.sync().unwrap();
+    let mut dev = vol.device().clone();
+    relink(&vol, &mut dev, &layout, "/victim/dir_link", root[1]);
+    relink(&vol, &mut dev, &layout, "/victim/file_link", root[1]);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    remove_dir_all(&mut vol, "/victim").unwrap();
+
+    assert_eq!(vol.cluster_chain(2).map(Result::unwrap).collect::<Vec<_>>(), root);
+    assert_eq!(vol.load_file("/f19", &mut [0u8; 4]).unwrap(), 4);
+    vol.sync().unwrap();
+    assert_eq!(fat_entry(vol.device(), &layout, 0, victim), 0);
+    for &cluster in &root {
+        assert_ne!(fat_entry(vol.device(), &layout, 0, cluster), 0);
+    }
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{fat_entry, small_volume};
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_dir, create_file, remove_file};
+
+#[test]
+fn frees_clusters_and_slots() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+
+    create_file(&mut vol, 2, "A long file name.txt", &[7u8; 1500]).unwrap();
+    create_file(&mut vol, 2, "keep.txt", b"keep").unwrap();
+    let entry = vol.open_path("/a long file name.txt").unwrap();
+    let location = entry.location.unwrap();
+    assert_eq!(location.lfn_slots, 2);
+    let clusters: Vec<u32> = vol.cluster_chain(entry.first_cluster).map(Result::unwrap).collect();
+    assert_eq!(clusters.len(), 3);
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 4));
+
+    remove_file(&mut vol, "/A LONG FILE NAME.TXT").unwrap();
+
+    assert_eq!(vol.open_path("/a long file name.txt").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.open_path("/keep.txt").unwrap().size, 4);
+    for cluster in clusters {
+        assert_eq!(fat_entry(vol.device(), &layout, 0, cluster), 0);
+        assert_eq!(fat_entry(vol.device(), &layout, 1, cluster), 0);
+    }
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 1));
+
+    // the short entry and both LFN slots are marked deleted
+    let root = vol.device().read(layout.cluster_lba(2));
+    for slot in 0..3 {
+        assert_eq!(root[slot * 32], 0xE5);
+    }
+    assert_ne!(root[3 * 32], 0xE5);
+}
+
+#[test]
+fn deleted_slots_are_reused() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "first.bin", b"1").unwrap();
+    create_file(&mut vol, 2, "second.bin", b"2").unwrap();
+    let pos = vol.open_path("/first.bin").unwrap().location.unwrap().pos;
+
+    remove_file(&mut vol, "first.bin").unwrap();
+    create_file(&mut vol, 2, "third.bin", b"3").unwrap();
+    assert_eq!(vol.open_path("/third.bin").unwrap().location.unwrap().pos, pos);
+}
+
+#[test]
+fn removes_empty_file() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count;
+
+    create_file(&mut vol, 2, "empty", b"").unwrap();
+    remove_file(&mut vol, "/empty").unwrap();
+    assert_eq!(vol.open_path("/empty").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.fsinfo().unwrap().free_count, free);
+}
+
+#[test]
+fn refuses_directories_and_read_only_files() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_dir(&mut vol, 2, "dir").unwrap();
+    assert_eq!(remove_file(&mut vol, "/dir").unwrap_err(), FatError::IsADirectory);
+    assert_eq!(remove_file(&mut vol, "/").unwrap_err(), FatError::IsADirectory);
+    assert_eq!(remove_file(&mut vol, "/missing").unwrap_err(), FatError::NotFound);
+
+    create_file(&mut vol, 2, "locked.txt", b"data").unwrap();
+    let pos = vol.open_path("/locked.txt").unwrap().location.unwrap().pos;
+    let mut dev = vol.device().clone();
+    let mut sector = dev.read(pos.lba);
+    sector[pos.offset + 11] |= 0x01;
+    dev.write(pos.lba, &sector);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    assert_eq!(remove_file(&mut vol, "/locked.txt").unwrap_err(), FatError::ReadOnly);
+    assert_eq!(vol.open_path("/locked.txt").unwrap().size, 4);
+}
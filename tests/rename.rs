@@ -0,0 +1,130 @@
+mod common;
+
+use common::small_volume;
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_dir, create_dir_all, create_file, rename};
+
+#[test]
+fn renames_within_directory() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "notes.txt", b"hello").unwrap();
+    let before = vol.open_path("/notes.txt").unwrap();
+
+    rename(&mut vol, "/notes.txt", "/Meeting notes from Monday.txt").unwrap();
+
+    assert_eq!(vol.open_path("/notes.txt").unwrap_err(), FatError::NotFound);
+    let after = vol.open_path("/meeting notes from monday.txt").unwrap();
+    assert_eq!(after.to_string(), "Meeting notes from Monday.txt");
+    assert_eq!(&after.name, b"MEETIN~1TXT");
+    assert_eq!(after.first_cluster, before.first_cluster);
+    assert_eq!(after.size, 5);
+    assert_eq!(after.attributes, before.attributes);
+    assert_eq!(after.created, before.created);
+    assert_eq!(vol.read_dir(2).count(), 1);
+}
+
+#[test]
+fn renames_case_only() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "readme.txt", b"x").unwrap();
+    rename(&mut vol, "README.TXT", "ReadMe.txt").unwrap();
+
+    let entries: Vec<_> = vol.read_dir(2).map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].to_string(), "ReadMe.txt");
+    assert_eq!(&entries[0].name, b"README  TXT");
+}
+
+#[test]
+fn refuses_existing_target() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_file(&mut vol, 2, "a.txt", b"a").unwrap();
+    create_file(&mut vol, 2, "b.txt", b"b").unwrap();
+    assert_eq!(rename(&mut vol, "/a.txt", "/B.TXT").unwrap_err(), FatError::AlreadyExists);
+    assert_eq!(rename(&mut vol, "/a.txt", "/b.txt/c").unwrap_err(), FatError::NotADirectory);
+    assert_eq!(rename(&mut vol, "/a.txt", "/missing/c").unwrap_err(), FatError::NotFound);
+    assert_eq!(rename(&mut vol, "/a.txt", "/bad?.txt").unwrap_err(), FatError::InvalidFileName);
+    assert_eq!(rename(&mut vol, "/", "/root").unwrap_err(), FatError::IsRootDirectory);
+    assert_eq!(vol.load_file("/a.txt", &mut [0u8; 1]).unwrap(), 1);
+}
+
+#[test]
+fn moves_file_between_directories() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    let inbox = create_dir(&mut vol, 2, "inbox").unwrap();
+    let archive = create_dir(&mut vol, 2, "archive").unwrap();
+    create_file(&mut vol, inbox, "report.csv", b"1,2,3").unwrap();
+    let free = vol.space_info(false).unwrap().free_clusters;
+
+    rename(&mut vol, "/inbox/report.csv", "/archive/2024 report.csv").unwrap();
+
+    assert_eq!(vol.read_dir(inbox).count(), 2);
+    let mut buf = [0u8; 5];
+    assert_eq!(vol.load_file("/archive/2024 report.csv", &mut buf).unwrap(), 5);
+    assert_eq!(&buf, b"1,2,3");
+    assert_eq!(vol.find_entry(archive, "2024 report.csv").unwrap().size, 5);
+    assert_eq!(vol.space_info(false).unwrap().free_clusters, free);
+}
+
+#[test]
+fn moved_directory_points_to_new_parent() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    let src = create_dir_all(&mut vol, "/a/src").unwrap();
+    let b = create_dir(&mut vol, 2, "b").unwrap();
+    create_file(&mut vol, src, "main.rs", b"fn main() {}").unwrap();
+
+    rename(&mut vol, "/a/src", "/b/source").unwrap();
+    assert_eq!(vol.find_entry(src, "..").unwrap().first_cluster, b);
+    assert_eq!(vol.open_path("/b/source/../source/main.rs").unwrap().size, 12);
+    assert_eq!(vol.open_path("/a/src").unwrap_err(), FatError::NotFound);
+
+    rename(&mut vol, "/b/source", "/top").unwrap();
+    assert_eq!(vol.find_entry(src, "..").unwrap().first_cluster, 0);
+    assert_eq!(vol.open_path("/top/..").unwrap().first_cluster, 2);
+}
+
+#[test]
+fn refuses_moving_directory_into_itself() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    create_dir_all(&mut vol, "/a/b/c").unwrap();
+    assert_eq!(rename(&mut vol, "/a", "/a/b/c/a").unwrap_err(), FatError::MoveIntoSubdirectory);
+    assert_eq!(rename(&mut vol, "/a", "/a/a").unwrap_err(), FatError::MoveIntoSubdirectory);
+    assert_eq!(rename(&mut vol, "/a/b/..", "/x").unwrap_err(), FatError::InvalidFileName);
+
+    rename(&mut vol, "/a/b/c", "/c").unwrap();
+    assert!(vol.open_path("/c").unwrap().is_dir());
+}
+
+#[test]
+fn failed_move_leaves_no_second_entry() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let moved = create_dir(&mut vol, 2, "moved").unwrap();
+    create_dir(&mut vol, 2, "dst").unwrap();
+
+    // corrupt the image: delete the `..` entry of the directory to move
+    vol.sync().unwrap();
+    let mut dev = vol.device().clone();
+    let pos = vol.find_entry(moved, "..").unwrap().location.unwrap().pos;
+    let mut sector = dev.read(pos.lba);
+    sector[pos.offset] = 0xE5;
+    dev.write(pos.lba, &sector);
+    let mut vol = Fat32Volume::open(dev).unwrap();
+
+    assert_eq!(rename(&mut vol, "/moved", "/dst/moved").unwrap_err(), FatError::NotFound);
+    assert_eq!(vol.open_path("/moved").unwrap().first_cluster, moved);
+    assert_eq!(vol.open_path("/dst/moved").unwrap_err(), FatError::NotFound);
+}
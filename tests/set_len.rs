@@ -0,0 +1,100 @@
+mod common;
+
+use common::{fat_entry, small_volume};
+use no_std::error::FatError;
+use no_std::volume::Fat32Volume;
+use no_std::write::{create_file, Fill, OpenOptions};
+
+#[test]
+fn shrinking_frees_tail_clusters() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+    create_file(&mut vol, 2, "big.bin", &[9u8; 2000]).unwrap();
+    let chain: Vec<u32> = {
+        let first = vol.open_path("/big.bin").unwrap().first_cluster;
+        vol.cluster_chain(first).map(Result::unwrap).collect()
+    };
+
+    let mut file = OpenOptions::new().read(true).write(true).open(&mut vol, "/big.bin").unwrap();
+    file.set_len(600, Fill::Zeros).unwrap();
+    assert_eq!(file.len(), 600);
+    file.close().unwrap();
+
+    let entry = vol.open_path("/big.bin").unwrap();
+    assert_eq!(entry.size, 600);
+    assert_eq!(fat_entry(vol.device(), &layout, 0, chain[1]), 0x0FFFFFFF);
+    assert_eq!(fat_entry(vol.device(), &layout, 1, chain[1]), 0x0FFFFFFF);
+    for &cluster in &chain[2..] {
+        assert_eq!(fat_entry(vol.device(), &layout, 0, cluster), 0);
+    }
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 2));
+    let mut buf = [0u8; 600];
+    vol.load_file("/big.bin", &mut buf).unwrap();
+    assert!(buf.iter().all(|&b| b == 9));
+}
+
+#[test]
+fn truncating_to_zero_clears_first_cluster() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+    create_file(&mut vol, 2, "big.bin", &[9u8; 2000]).unwrap();
+
+    let mut file = OpenOptions::new().write(true).open(&mut vol, "/big.bin").unwrap();
+    file.set_len(0, Fill::Zeros).unwrap();
+    file.write(b"again").unwrap();
+    file.close().unwrap();
+
+    let entry = vol.open_path("/big.bin").unwrap();
+    assert_eq!(entry.size, 5);
+    assert_eq!(vol.cluster_chain(entry.first_cluster).count(), 1);
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 1));
+
+    let mut file = OpenOptions::new().write(true).open(&mut vol, "/big.bin").unwrap();
+    file.set_len(0, Fill::Undefined).unwrap();
+    drop(file);
+    let entry = vol.open_path("/big.bin").unwrap();
+    assert_eq!((entry.size, entry.first_cluster), (0, 0));
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free));
+}
+
+#[test]
+fn growing_zero_fills_or_only_allocates() {
+    let (dev, layout) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    let free = vol.fsinfo().unwrap().free_count.unwrap();
+
+    // leave stale data in the clusters the file will get
+    create_file(&mut vol, 2, "stale.bin", &[0xAA; 2048]).unwrap();
+    no_std::write::remove_file(&mut vol, "/stale.bin").unwrap();
+
+    let mut file = OpenOptions::new().read(true).write(true).create(true).open(&mut vol, "/a.bin").unwrap();
+    file.write(b"abc").unwrap();
+    file.set_len(1500, Fill::Zeros).unwrap();
+    assert_eq!(file.position(), 3);
+    let mut buf = [0xFFu8; 1500];
+    assert_eq!(file.read(&mut buf).unwrap(), 1497);
+    assert!(buf[..1497].iter().all(|&b| b == 0));
+    file.close().unwrap();
+
+    let mut file = OpenOptions::new().write(true).create(true).open(&mut vol, "/b.bin").unwrap();
+    file.set_len(1100, Fill::Undefined).unwrap();
+    file.close().unwrap();
+    let entry = vol.open_path("/b.bin").unwrap();
+    assert_eq!(entry.size, 1100);
+    let chain: Vec<u32> = vol.cluster_chain(entry.first_cluster).map(Result::unwrap).collect();
+    assert_eq!(chain.len(), 3);
+    assert_eq!(fat_entry(vol.device(), &layout, 1, chain[2]), 0x0FFFFFFF);
+    assert_eq!(vol.fsinfo().unwrap().free_count, Some(free - 6));
+}
+
+#[test]
+fn requires_write_access() {
+    let (dev, _) = small_volume();
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "a.txt", b"abc").unwrap();
+
+    let mut file = OpenOptions::new().read(true).open(&mut vol, "/a.txt").unwrap();
+    assert_eq!(file.set_len(0, Fill::Zeros).unwrap_err(), FatError::NotWritable);
+}
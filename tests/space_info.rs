@@ -0,0 +1,58 @@
+mod common;
+
+use common::{set_fat_entry, small_volume};
+use no_std::block::BlockDevice;
+use no_std::fsinfo::FsInfo;
+use no_std::volume::{Fat32Volume, FreeCountSource};
+use no_std::write::create_file;
+
+#[test]
+fn scans_fat_for_free_and_bad_clusters() {
+    let (mut dev, layout) = small_volume();
+    set_fat_entry(&mut dev, &layout, 1000, 0x0FFFFFF7);
+    set_fat_entry(&mut dev, &layout, layout.cluster_count() + 1, 0x0FFFFFF7);
+
+    let mut vol = Fat32Volume::open(dev).unwrap();
+    create_file(&mut vol, 2, "LOG.TXT", &[7u8; 2000]).unwrap();
+
+    let info = vol.space_info(false).unwrap();
+    assert_eq!(info.source, FreeCountSource::FatScan);
+    assert_eq!(info.total_clusters, layout.cluster_count());
+    // root directory, four file clusters and two bad clusters
+    assert_eq!(info.free_clusters, layout.cluster_count() - 7);
+    assert_eq!(info.bad_clusters, Some(2));
+    assert_eq!(info.cluster_size, 512);
+    assert_eq!(info.free_bytes(), info.free_clusters as u64 * 512);
+    assert_eq!(info.used_bytes(), 7 * 512);
+}
+
+#[test]
+fn trusts_fsinfo_only_when_valid() {
+    let (mut dev, layout) = small_volume();
+    let mut fsinfo = [0u8; 512];
+    dev.read_sector(1, &mut fsinfo);
+    fsinfo[488..492].copy_from_slice(&1234u32.to_le_bytes());
+    dev.write_sector(1, &fsinfo);
+
+    let vol = Fat32Volume::open(dev.clone()).unwrap();
+    assert_eq!(vol.fsinfo().unwrap(), FsInfo { free_count: Some(1234), next_free: Some(3) });
+    let info = vol.space_info(true).unwrap();
+    assert_eq!(info.source, FreeCountSource::FsInfo);
+    assert_eq!(info.free_clusters, 1234);
+    assert_eq!(info.bad_clusters, None);
+
+    // implausible count: larger than the volume
+    fsinfo[488..492].copy_from_slice(&u32::MAX.wrapping_sub(1).to_le_bytes());
+    dev.write_sector(1, &fsinfo);
+    let info = Fat32Volume::open(dev.clone()).unwrap().space_info(true).unwrap();
+    assert_eq!(info.source, FreeCountSource::FatScan);
+    assert_eq!(info.free_clusters, layout.cluster_count() - 1);
+
+    // broken structure signature
+    fsinfo[488..492].copy_from_slice(&1234u32.to_le_bytes());
+    fsinfo[484] = 0;
+    dev.write_sector(1, &fsinfo);
+    let vol = Fat32Volume::open(dev).unwrap();
+    assert!(vol.fsinfo().is_err());
+    assert_eq!(vol.space_info(true).unwrap().source, FreeCountSource::FatScan);
+}
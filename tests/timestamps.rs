@@ -0,0 +1,60 @@
+mod common;
+
+use std::cell::Cell;
+
+use common::small_volume;
+use no_std::time::{DateTime, TimeSource, FAT_EPOCH};
+use no_std::volume::Fat32Volume;
+use no_std::write::create_file;
+
+/// A clock owned by the test rather than a `'static` one.
+struct Rtc(Cell<DateTime>);
+
+impl TimeSource for Rtc {
+    fn now(&self) -> DateTime {
+        self.0.get()
+    }
+}
+
+const NOW: DateTime = DateTime {
+    year: 2024,
+    month: 7,
+    day: 14,
+    hour: 13,
+    minute: 37,
+    second: 43,
+    millis: 250,
+};
+
+#[test]
+fn encodes_and_decodes_dos_timestamps() {
+    let t = NOW;
+    assert_eq!(t.fat_date(), (44 << 9) | (7 << 5) | 14);
+    assert_eq!(t.fat_time(), (13 << 11) | (37 << 5) | 21);
+    assert_eq!(t.fat_tenths(), 125);
+    assert_eq!(DateTime::from_fat(t.fat_date(), t.fat_time(), t.fat_tenths()), t);
+
+    // without the tenths byte, odd seconds and milliseconds are lost
+    let coarse = DateTime::from_fat(t.fat_date(), t.fat_time(), 0);
+    assert_eq!((coarse.second, coarse.millis), (42, 0));
+    assert_eq!(DateTime::from_fat(0x0021, 0, 0), FAT_EPOCH);
+}
+
+#[test]
+fn stamps_created_files_with_time_source() {
+    let (dev, _) = small_volume();
+    let rtc = Rtc(Cell::new(FAT_EPOCH));
+    let mut vol = Fat32Volume::open_with_time_source(dev, &rtc).unwrap();
+    create_file(&mut vol, 2, "OLD.TXT", b"x").unwrap();
+    rtc.0.set(NOW);
+    create_file(&mut vol, 2, "NEW.TXT", b"y").unwrap();
+
+    let old = vol.open_path("OLD.TXT").unwrap();
+    assert_eq!(old.created, FAT_EPOCH);
+    assert_eq!(old.modified, FAT_EPOCH);
+
+    let new = vol.open_path("NEW.TXT").unwrap();
+    assert_eq!(new.created, NOW);
+    assert_eq!(new.modified, DateTime { second: 42, millis: 0, ..NOW });
+    assert_eq!(new.accessed, DateTime { hour: 0, minute: 0, second: 0, millis: 0, ..NOW });
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::small_volume;
+use no_std::block::BlockDevice;
+use no_std::volume::Fat32Volume;
+
+#[test]
+fn exposes_boot_sector_metadata() {
+    let (dev, _) = small_volume();
+    let vol = Fat32Volume::open(dev).unwrap();
+
+    assert_eq!(vol.oem_name(), b"MSWIN4.1");
+    assert_eq!(vol.media_descriptor(), 0xF8);
+    assert_eq!(vol.ext_flags(), 0);
+    assert!(vol.boot.fat_mirroring());
+    assert_eq!(vol.fs_version(), (0, 0));
+    assert_eq!(vol.fsinfo_sector(), 1);
+    assert_eq!(vol.backup_boot_sector(), 6);
+    assert_eq!(vol.volume_serial(), Some(0xCAFEBABE));
+    assert_eq!(vol.fs_type(), Some(b"FAT32   "));
+    assert_eq!(vol.root_volume_label().unwrap(), None);
+    assert_eq!(vol.volume_label().unwrap(), Some(*b"TESTVOLUME "));
+}
+
+#[test]
+fn root_label_takes_precedence() {
+    let (mut dev, layout) = small_volume();
+    let mut root = [0u8; 512];
+    // a deleted label and an LFN slot must both be ignored
+    root[0] = 0xE5;
+    root[1..11].copy_from_slice(b"LD LABEL  ");
+    root[11] = 0x08;
+    root[32] = 0x41;
+    root[32 + 11] = 0x0F;
+    root[64..75].copy_from_slice(b"RENAMED    ");
+    root[64 + 11] = 0x08;
+    dev.write_sector(layout.cluster_lba(2), &root);
+
+    let vol = Fat32Volume::open(dev).unwrap();
+    assert_eq!(vol.root_volume_label().unwrap(), Some(*b"RENAMED    "));
+    assert_eq!(vol.volume_label().unwrap(), Some(*b"RENAMED    "));
+    assert_eq!(vol.boot.volume_label, Some(*b"TESTVOLUME "));
+    assert_eq!(vol.read_dir(2).count(), 0);
+}
@@ -51,6 +51,7 @@ fn write_file_to_real_image() {
         content,
     )
         .expect("Failed to create file");
+    volume.sync().expect("Failed to sync volume");
 
     println!("File written successfully!");
 }